@@ -17,6 +17,18 @@ impl builder::Builder for Logging {
         }
     }
 
+    fn pkgconfig_file(&self, file: &ConfigureFile) {
+        eprintln!(
+            " > Generating pkg-config file {}: {} bytes",
+            file.build_dir.join(&file.filename),
+            file.content.len(),
+        );
+        eprintln!(
+            " > Installing pkg-config file to {}: {}",
+            file.install_dir, file.filename
+        );
+    }
+
     fn install_headers(
         &self,
         install_dir: &picomeson::path::Path,
@@ -34,6 +46,7 @@ impl builder::Builder for Logging {
             target.install_dir.join(&target.filename),
             target.sources.len(),
         );
+        log_links(target);
     }
 
     fn build_static_library(&self, target: &builder::BuildTarget) {
@@ -47,4 +60,21 @@ impl builder::Builder for Logging {
             );
         }
     }
+
+    fn build_shared_library(&self, target: &builder::BuildTarget) {
+        eprintln!(
+            " > Building shared library {}: {} sources",
+            target.install_dir.join(&target.filename),
+            target.sources.len(),
+        );
+        log_links(target);
+    }
+}
+
+fn log_links(target: &builder::BuildTarget) {
+    let libs = target.link_whole.iter().chain(&target.link_with);
+    let libs = libs.map(|lib| lib.filename.to_string()).collect::<Vec<_>>();
+    if !libs.is_empty() {
+        eprintln!("   linking with {}", libs.join(", "));
+    }
 }
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use std::str::FromStr;
 
 use anyhow::Context;
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(ValueEnum, Clone, Debug)]
 pub enum BuildType {
@@ -44,7 +44,11 @@ impl Display for BuildType {
 #[command(name = "meson")]
 #[command(about = "A minimal Meson build system implementation")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Build type to use
     #[arg(long, value_name = "build type", default_value = "debug")]
     pub buildtype: BuildType,
@@ -53,18 +57,48 @@ pub struct Args {
     #[arg(long, value_name = "dir", default_value = "/usr/local")]
     pub prefix: PathBuf,
 
+    /// Cross file describing the host machine (can be used multiple times)
+    #[arg(long, value_name = "file")]
+    pub cross_file: Vec<PathBuf>,
+
+    /// Native file describing the build machine (can be used multiple times)
+    #[arg(long, value_name = "file")]
+    pub native_file: Vec<PathBuf>,
+
     /// Set project options (can be used multiple times)
     #[arg(short = 'D', value_name = "option=value")]
     pub define: Vec<Define>,
 
     /// Build directory
-    pub build_dir: PathBuf,
+    #[arg(required = true)]
+    pub build_dir: Option<PathBuf>,
 
     /// Source directory (defaults to current directory if not specified)
     #[arg(default_value = ".")]
     pub source_dir: PathBuf,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Format meson build files
+    Format(FormatArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct FormatArgs {
+    /// Only check whether the files are formatted, exit with an error if not
+    #[arg(short = 'q', long, alias = "check-only", conflicts_with = "inplace")]
+    pub check: bool,
+
+    /// Format the files in place instead of printing them
+    #[arg(short, long)]
+    pub inplace: bool,
+
+    /// Files to format, a directory stands for its meson.build
+    #[arg(default_value = "meson.build")]
+    pub sources: Vec<PathBuf>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Define {
     pub key: String,
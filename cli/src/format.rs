@@ -0,0 +1,45 @@
+use std::fs;
+use std::io::Write as _;
+
+use anyhow::{Context, bail};
+use picomeson::formatter::{FormatOptions, format};
+
+use crate::cli::FormatArgs;
+
+pub fn run(args: FormatArgs) -> anyhow::Result<()> {
+    let options = FormatOptions::default();
+    let mut unformatted = 0;
+
+    for source in args.sources {
+        let path = if source.is_dir() {
+            source.join("meson.build")
+        } else {
+            source
+        };
+
+        let input = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let output =
+            format(&input, &options).map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?;
+
+        if args.check {
+            if output != input {
+                println!("Would reformat {}", path.display());
+                unformatted += 1;
+            }
+        } else if args.inplace {
+            if output != input {
+                fs::write(&path, output)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+        } else {
+            std::io::stdout().write_all(output.as_bytes())?;
+        }
+    }
+
+    if unformatted > 0 {
+        bail!("{unformatted} file(s) are not formatted");
+    }
+
+    Ok(())
+}
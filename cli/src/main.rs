@@ -1,5 +1,6 @@
 mod builder;
 mod cli;
+mod format;
 mod runtime;
 
 use builder::Logging;
@@ -8,7 +9,16 @@ use runtime::Sandbox;
 fn main() -> anyhow::Result<()> {
     let args = cli::parse();
 
+    if let Some(cli::Command::Format(format_args)) = args.command {
+        return format::run(format_args);
+    }
+    // Required by clap unless a subcommand is given
+    let build_dir = args.build_dir.unwrap_or_default();
+
+    std::fs::create_dir_all(&build_dir)?;
+
     let mut builder = picomeson::Meson::new(Sandbox, Logging);
+    builder.cache_compiler_checks(true);
 
     // Add buildtype option
     builder.option("buildtype", args.buildtype.to_string());
@@ -16,6 +26,13 @@ fn main() -> anyhow::Result<()> {
     // Add prefix option
     builder.option("prefix", args.prefix.to_string_lossy());
 
+    for file in args.cross_file {
+        builder.cross_file(file.to_string_lossy());
+    }
+    for file in args.native_file {
+        builder.native_file(file.to_string_lossy());
+    }
+
     // Add user-defined options
     for d in args.define {
         builder.option(d.key, d.value);
@@ -23,7 +40,7 @@ fn main() -> anyhow::Result<()> {
 
     builder.build(
         args.source_dir.to_string_lossy(),
-        args.build_dir.to_string_lossy(),
+        build_dir.to_string_lossy(),
     )?;
 
     Ok(())
@@ -1,9 +1,10 @@
 use std::env::consts::{ARCH, OS};
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::process::Command;
-use std::{env, fs};
+use std::{env, fs, thread};
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 use picomeson::path::Path as OsPath;
 use picomeson::runtime::{self, CompilerInfo};
 use tempfile::tempdir;
@@ -30,6 +31,8 @@ impl runtime::Runtime for Sandbox {
             system: OS.into(),
             cpu: ARCH.into(),
             endian: ENDIAN.into(),
+            kernel: None,
+            subsystem: None,
         })
     }
 
@@ -41,6 +44,8 @@ impl runtime::Runtime for Sandbox {
             system,
             cpu,
             endian,
+            kernel: None,
+            subsystem: None,
         })
     }
 
@@ -99,4 +104,60 @@ impl runtime::Runtime for Sandbox {
             returncode: output.status.code().unwrap_or(-1) as i64,
         })
     }
+
+    fn run_commands(
+        &self,
+        commands: &[(&OsPath, &[&str])],
+    ) -> Vec<runtime::Result<runtime::RunCommandOutput>> {
+        let jobs = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+        let mut outputs = Vec::with_capacity(commands.len());
+        for batch in commands.chunks(jobs) {
+            thread::scope(|scope| {
+                let handles = batch
+                    .iter()
+                    .map(|&(cmd, args)| scope.spawn(move || self.run_command(cmd, args)))
+                    .collect::<Vec<_>>();
+                outputs.extend(handles.into_iter().map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow!("Command panicked")))
+                }));
+            });
+        }
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use picomeson::runtime::Runtime as _;
+
+    use super::*;
+
+    #[test]
+    fn test_run_commands() {
+        let cc = OsPath::from("cc");
+        let other = OsPath::from("ld");
+        let count = 2 * thread::available_parallelism().map_or(1, NonZeroUsize::get) + 1;
+
+        // Enough commands for several batches, each failing in its own way
+        let commands = (0..count)
+            .map(|i| match i % 3 {
+                0 => (&cc, &["--version"][..]),
+                1 => (&cc, &["--no-such-option"][..]),
+                _ => (&other, &["--version"][..]),
+            })
+            .collect::<Vec<_>>();
+        let outputs = Sandbox.run_commands(&commands);
+
+        assert_eq!(outputs.len(), count);
+        for (i, output) in outputs.iter().enumerate() {
+            match i % 3 {
+                0 => assert_eq!(output.as_ref().unwrap().returncode, 0),
+                1 => assert_ne!(output.as_ref().unwrap().returncode, 0),
+                _ => assert!(output.is_err()),
+            }
+        }
+    }
 }
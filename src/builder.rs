@@ -1,5 +1,6 @@
-pub use crate::interpreter::builtins::build_target::BuildTarget;
+pub use crate::interpreter::builtins::build_target::{BuildTarget, TargetType};
 pub use crate::interpreter::builtins::config_data::ConfigureFile;
+pub use crate::interpreter::builtins::dependency::{Dependency, DependencyKind};
 use crate::path::Path;
 
 /// Builder abstraction for generating build system output
@@ -20,6 +21,19 @@ pub trait Builder: 'static {
     ///   include directories, compile flags, and output location
     fn build_static_library(&self, target: &BuildTarget);
 
+    /// Generates build instructions for building a shared library
+    ///
+    /// This method should generate the necessary build steps to compile
+    /// source files into position independent object files and link them
+    /// into a shared library, recording the soname and creating the aliases
+    /// of the target.
+    ///
+    /// # Arguments
+    /// * `target` - The build target containing information about sources,
+    ///   include directories, compile flags, output location, soname,
+    ///   aliases and Darwin versions
+    fn build_shared_library(&self, target: &BuildTarget);
+
     /// Generates build instructions for building an executable
     ///
     /// This method should generate the necessary build steps to compile
@@ -41,6 +55,18 @@ pub trait Builder: 'static {
     /// * `file` - The `ConfigureFile` describing where to write and what to write
     fn configure_file(&self, file: &ConfigureFile);
 
+    /// Generates build instructions for generating a pkg-config file
+    ///
+    /// This method should generate a build step that writes the `.pc` file
+    /// described by `file` to the build directory, the same way
+    /// `configure_file` does, and installs it to `file.install_dir`, which
+    /// is `libdir/pkgconfig` unless the project chose another directory.
+    ///
+    /// # Arguments
+    /// * `file` - The `ConfigureFile` holding the name and content of the
+    ///   `.pc` file and where to install it
+    fn pkgconfig_file(&self, file: &ConfigureFile);
+
     /// Generates build instructions for installing header files
     ///
     /// This method should generate build steps to copy header files from
@@ -0,0 +1,496 @@
+//! Pretty printer for meson build files in the style of `meson format`
+//!
+//! Files are parsed into the lossless syntax tree and printed back with
+//! normalised whitespace. Expressions are kept on one line when they fit,
+//! otherwise the outermost argument list, array or dictionary is broken
+//! into one element per line with a trailing comma. Comments are kept, and
+//! runs of blank lines are collapsed into one.
+
+use alloc::string::{String, ToString as _};
+use alloc::vec::Vec;
+
+use crate::parser::cst::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, TriviaKind};
+use crate::parser::{ParseError, Parser, Token};
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Lines longer than this are broken up where possible
+    pub max_line_length: usize,
+    /// The string used for one level of indentation
+    pub indent: String,
+    /// Sort the string arguments of `files()` calls
+    pub sort_files: bool,
+    /// Rewrite double quoted strings using single quotes
+    pub simplify_string_literals: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            max_line_length: 80,
+            indent: "    ".into(),
+            sort_files: true,
+            simplify_string_literals: true,
+        }
+    }
+}
+
+/// Formats the meson source in `source`
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, ParseError> {
+    let root = Parser::new(source).parse_cst()?;
+
+    let mut formatter = Formatter {
+        options,
+        out: String::new(),
+    };
+    formatter.block(&root, 0);
+    if let Some(eof) = root.last_token() {
+        formatter.comments(eof, 0);
+    }
+    if !formatter.out.is_empty() {
+        formatter.out.push('\n');
+    }
+
+    Ok(formatter.out)
+}
+
+struct Formatter<'a> {
+    options: &'a FormatOptions,
+    out: String,
+}
+
+impl Formatter<'_> {
+    fn block(&mut self, node: &SyntaxNode, indent: usize) {
+        for statement in node.nodes() {
+            self.statement(statement, indent);
+        }
+    }
+
+    fn statement(&mut self, node: &SyntaxNode, indent: usize) {
+        self.comments(first_token(node), indent);
+        self.start_line(indent);
+
+        match node.kind {
+            NodeKind::If => {
+                let mut nodes = node.nodes();
+                self.push("if ");
+                self.header(nodes.next(), indent);
+                self.body(nodes.next(), indent);
+
+                for clause in nodes {
+                    let keyword = first_token(clause);
+                    self.comments(keyword, indent + 1);
+                    self.start_line(indent);
+                    let mut nodes = clause.nodes();
+                    if clause.kind == NodeKind::ElifClause {
+                        self.push("elif ");
+                        self.header(nodes.next(), indent);
+                    } else {
+                        self.push("else");
+                    }
+                    self.body(nodes.last(), indent);
+                }
+
+                self.end(node, indent);
+            }
+            NodeKind::Foreach => {
+                self.push("foreach ");
+                let mut tokens = node.tokens().skip(1);
+                for token in tokens.by_ref() {
+                    self.inline_comments(token, indent);
+                    match token.token {
+                        Token::Colon => break,
+                        Token::Comma => self.push(", "),
+                        _ => self.push(&token.text),
+                    }
+                }
+                self.push(" : ");
+
+                let mut nodes = node.nodes();
+                self.header(nodes.next(), indent);
+                self.body(nodes.next(), indent);
+                self.end(node, indent);
+            }
+            NodeKind::Assignment | NodeKind::AddAssignment => {
+                self.push(&first_token(node).text);
+                for token in node.tokens() {
+                    self.inline_comments(token, indent);
+                    self.push(if token.token == Token::Assign {
+                        " = "
+                    } else {
+                        " += "
+                    });
+                }
+                let value = child(node, 1);
+                self.inline_comments(first_token(value), indent);
+                self.expr(value, indent);
+            }
+            _ => self.expr(node, indent),
+        }
+    }
+
+    /// Writes an `if`, `elif` or `foreach` header expression
+    fn header(&mut self, node: Option<&SyntaxNode>, indent: usize) {
+        if let Some(node) = node {
+            self.inline_comments(first_token(node), indent);
+            self.expr(node, indent);
+        }
+    }
+
+    fn body(&mut self, block: Option<&SyntaxNode>, indent: usize) {
+        if let Some(block) = block {
+            self.block(block, indent + 1);
+        }
+    }
+
+    /// Writes the `endif` or `endforeach` closing `node`
+    fn end(&mut self, node: &SyntaxNode, indent: usize) {
+        if let Some(end) = node.last_token() {
+            self.comments(end, indent + 1);
+            self.start_line(indent);
+            self.push(&end.text);
+        }
+    }
+
+    /// Writes an expression, breaking it over several lines if it does not
+    /// fit or contains comments. The first token's comments are left to the
+    /// caller.
+    fn expr(&mut self, node: &SyntaxNode, indent: usize) {
+        if let Some(flat) = self.flat(node)
+            && self.column() + flat.chars().count() <= self.options.max_line_length
+        {
+            self.push(&flat);
+            return;
+        }
+
+        match node.kind {
+            NodeKind::Array | NodeKind::Dict | NodeKind::ArgumentList => {
+                self.list(node, indent, false)
+            }
+            NodeKind::FunctionCall => {
+                let name = child(node, 0);
+                self.push(&first_token(name).text);
+                let args = child(node, 1);
+                self.inline_comments(first_token(args), indent);
+                let sort = self.sorts_files(node);
+                self.list(args, indent, sort);
+            }
+            NodeKind::Literal | NodeKind::Name => self.push(&self.token_text(first_token(node))),
+            _ => {
+                // Everything else is written piece by piece so that nested
+                // lists can break
+                let mut first = true;
+                for child in &node.children {
+                    match child {
+                        SyntaxElement::Node(child) => {
+                            if !first {
+                                self.inline_comments(first_token(child), indent);
+                            }
+                            self.expr(child, indent);
+                        }
+                        SyntaxElement::Token(token) => {
+                            if !first {
+                                self.inline_comments(token, indent);
+                            }
+                            self.push(&separated(node.kind, token));
+                        }
+                    }
+                    first = false;
+                }
+            }
+        }
+    }
+
+    /// Writes an array, dictionary or argument list with one element per line
+    fn list(&mut self, node: &SyntaxNode, indent: usize, sort: bool) {
+        let mut elements = node.nodes().collect::<Vec<_>>();
+        if sort {
+            elements.sort_by_cached_key(|e| first_token(e).text.to_lowercase());
+        }
+        let width = elements
+            .iter()
+            .filter(|e| e.kind == NodeKind::KeywordArgument)
+            .map(|e| first_token(e).text.chars().count())
+            .max();
+
+        let mut elements = elements.into_iter();
+        let close = node.last_token();
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(_) => {
+                    // Sorting only happens without comments, so the elements
+                    // can be taken in sorted order here
+                    let Some(element) = elements.next() else {
+                        continue;
+                    };
+                    self.comments(first_token(element), indent + 1);
+                    self.start_line(indent + 1);
+                    match (element.kind, width) {
+                        (NodeKind::KeywordArgument, Some(width)) => {
+                            self.keyword_argument(element, indent + 1, width)
+                        }
+                        _ => self.expr(element, indent + 1),
+                    }
+                    self.push(",");
+                }
+                SyntaxElement::Token(token) if close.is_some_and(|c| core::ptr::eq(c, token)) => {
+                    self.comments(token, indent + 1);
+                    self.start_line(indent);
+                    self.push(&token.text);
+                }
+                SyntaxElement::Token(token) => match token.token {
+                    Token::Comma => self.comments(token, indent + 1),
+                    _ => self.push(&token.text),
+                },
+            }
+        }
+    }
+
+    /// Writes `name: value` with the value aligned to the longest keyword
+    fn keyword_argument(&mut self, node: &SyntaxNode, indent: usize, width: usize) {
+        let name = first_token(node);
+        self.push(&name.text);
+        self.push(":");
+        for token in node.tokens().skip(1) {
+            self.inline_comments(token, indent);
+        }
+        let pad = width - name.text.chars().count();
+        self.push(&" ".repeat(pad + 1));
+
+        let value = child(node, 0);
+        self.inline_comments(first_token(value), indent);
+        self.expr(value, indent);
+    }
+
+    /// Renders `node` on a single line, or `None` if it contains comments
+    fn flat(&self, node: &SyntaxNode) -> Option<String> {
+        let mut text = String::new();
+        self.flat_into(node, true, &mut text).then_some(text)
+    }
+
+    fn flat_into(&self, node: &SyntaxNode, first: bool, text: &mut String) -> bool {
+        if node.kind == NodeKind::FunctionCall && self.sorts_files(node) {
+            let mut args = child(node, 1).nodes().collect::<Vec<_>>();
+            args.sort_by_cached_key(|arg| first_token(arg).text.to_lowercase());
+            let args = args
+                .into_iter()
+                .map(|arg| self.token_text(first_token(arg)))
+                .collect::<Vec<_>>();
+            text.push_str(&first_token(node).text);
+            text.push('(');
+            text.push_str(&args.join(", "));
+            text.push(')');
+            return true;
+        }
+
+        let list = matches!(
+            node.kind,
+            NodeKind::Array | NodeKind::Dict | NodeKind::ArgumentList
+        );
+        let last = node.children.len().saturating_sub(1);
+
+        for (i, child) in node.children.iter().enumerate() {
+            let first = first && i == 0;
+            match child {
+                SyntaxElement::Node(child) => {
+                    if !self.flat_into(child, first, text) {
+                        return false;
+                    }
+                }
+                SyntaxElement::Token(token) => {
+                    if !first && has_comments(token) {
+                        return false;
+                    }
+                    // Trailing commas are dropped on a single line
+                    if list && token.token == Token::Comma && i + 1 == last {
+                        continue;
+                    }
+                    text.push_str(&match node.kind {
+                        NodeKind::Literal => self.token_text(token),
+                        kind => separated(kind, token),
+                    });
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Whether `node` is a `files()` call with only string arguments
+    fn sorts_files(&self, node: &SyntaxNode) -> bool {
+        self.options.sort_files
+            && first_token(node).token == Token::Identifier("files".into())
+            && node.nodes().nth(1).is_some_and(|args| {
+                args.nodes().all(|arg| {
+                    arg.kind == NodeKind::Literal
+                        && matches!(first_token(arg).token, Token::String(_))
+                }) && args.tokens().all(|t| !has_comments(t))
+                    && args.nodes().all(|arg| !has_comments(first_token(arg)))
+            })
+    }
+
+    fn token_text(&self, token: &SyntaxToken) -> String {
+        let text = &token.text;
+        if self.options.simplify_string_literals
+            && text.len() >= 2
+            && text.starts_with('"')
+            && !text.starts_with("\"\"\"")
+        {
+            let inner = &text[1..text.len() - 1];
+            if !inner.contains(['\'', '\\']) {
+                return alloc::format!("'{inner}'");
+            }
+        }
+        text.clone()
+    }
+
+    /// Writes the comments and blank lines preceding a token that starts a
+    /// line. A comment on the same line as the previous token stays there.
+    fn comments(&mut self, token: &SyntaxToken, indent: usize) {
+        let mut newlines = 0;
+        for trivia in &token.leading_trivia {
+            match trivia.kind {
+                TriviaKind::Newline => newlines += 1,
+                TriviaKind::Comment if newlines == 0 && !self.out.is_empty() => {
+                    self.push(" ");
+                    self.push(trivia.text.trim_end());
+                }
+                TriviaKind::Comment => {
+                    if newlines > 1 {
+                        self.blank_line();
+                    }
+                    self.start_line(indent);
+                    self.push(trivia.text.trim_end());
+                    newlines = 0;
+                }
+                _ => {}
+            }
+        }
+        if newlines > 1 {
+            self.blank_line();
+        }
+    }
+
+    /// Writes comments found in the middle of an expression, continuing the
+    /// expression on the next line
+    fn inline_comments(&mut self, token: &SyntaxToken, indent: usize) {
+        for trivia in &token.leading_trivia {
+            if trivia.kind == TriviaKind::Comment {
+                let line = self.current_line();
+                if !line.trim().is_empty() && !line.ends_with(' ') {
+                    self.push(" ");
+                }
+                self.push(trivia.text.trim_end());
+                self.start_line(indent + 1);
+            }
+        }
+    }
+
+    fn start_line(&mut self, indent: usize) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        for _ in 0..indent {
+            self.out.push_str(&self.options.indent);
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn current_line(&self) -> &str {
+        let start = self.out.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        &self.out[start..]
+    }
+
+    fn column(&self) -> usize {
+        self.current_line().chars().count()
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+}
+
+/// A token with the spacing it gets inside a `kind` node
+fn separated(kind: NodeKind, token: &SyntaxToken) -> String {
+    match (kind, &token.token) {
+        (_, Token::Comma) => ", ".into(),
+        (NodeKind::Binary, Token::Not) => " not".into(),
+        (NodeKind::Binary, _) | (NodeKind::Ternary, _) => alloc::format!(" {} ", token.text),
+        (NodeKind::Unary, Token::Not) => "not ".into(),
+        (NodeKind::DictEntry | NodeKind::KeywordArgument, Token::Colon) => ": ".into(),
+        _ => token.text.to_string(),
+    }
+}
+
+fn has_comments(token: &SyntaxToken) -> bool {
+    token
+        .leading_trivia
+        .iter()
+        .any(|t| t.kind == TriviaKind::Comment)
+}
+
+fn child(node: &SyntaxNode, n: usize) -> &SyntaxNode {
+    node.nodes()
+        .nth(n)
+        .unwrap_or_else(|| unreachable!("{:?} is missing child {n}", node.kind))
+}
+
+fn first_token(node: &SyntaxNode) -> &SyntaxToken {
+    node.first_token()
+        .unwrap_or_else(|| unreachable!("{:?} has no tokens", node.kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let input = r#"# header
+project('foo', "c",
+  version : '1.0', default_options : ['warning_level=3', 'c_std=c11', 'b_lto=true'])
+
+
+srcs = files('b.c', 'a.c',)
+deps = {
+    # aarch64
+    'arm64' : 'aarch64',  # trailing
+}
+if x # why
+  y = [1, 2,]
+    # lonely
+endif
+z = 'a' +  # why
+  'b'
+"#;
+        let expected = r#"# header
+project(
+    'foo',
+    'c',
+    version:         '1.0',
+    default_options: ['warning_level=3', 'c_std=c11', 'b_lto=true'],
+)
+
+srcs = files('a.c', 'b.c')
+deps = {
+    # aarch64
+    'arm64': 'aarch64', # trailing
+}
+if x # why
+    y = [1, 2]
+    # lonely
+endif
+z = 'a' + # why
+    'b'
+"#;
+        let options = FormatOptions::default();
+        let formatted = format(input, &options).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted, &options).unwrap(), formatted);
+    }
+}
@@ -1,7 +1,7 @@
-use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::{String, ToString as _};
 use alloc::vec::Vec;
+use alloc::{format, vec};
 use core::cell::{Ref, RefCell};
 use core::fmt;
 
@@ -9,25 +9,34 @@ use as_any::Downcast;
 use hashbrown::HashMap;
 
 use crate::builder::Builder;
-use crate::parser::{BinaryOperator, Statement, UnaryOperator, Value as AstValue};
+use crate::machine_file::{MachineFiles, MachineValue};
+use crate::parser::{
+    BinaryOperator, Dict, Location, Span, Spanned, Statement, UnaryOperator, Value as AstValue,
+};
 use crate::path::Path;
 use crate::runtime::Runtime;
 
 pub(crate) mod builtins;
 
 use builtins::add_languages::add_languages;
-use builtins::build_target::{custom_target, executable, static_library};
+use builtins::build_target::{
+    both_libraries, custom_target, executable, library, shared_library, static_library,
+};
+use builtins::compiler::cache::CheckCache;
 use builtins::config_data::{configuration_data, configure_file};
 use builtins::debug::{assert, error as error_fn, message, warning};
+use builtins::dependency::{declare_dependency, dependency};
+use builtins::disabler::{Disabler, disabler, is_disabled, is_disabler};
 use builtins::env::environment;
 use builtins::external_program::find_program;
+use builtins::feature::{Feature, FeatureState};
 use builtins::files::files;
 use builtins::filesystem::filesystem;
 use builtins::import::import;
 use builtins::include_directories::include_directories;
 use builtins::install_headers::install_headers;
 use builtins::join_paths::join_paths;
-use builtins::machine::{build_machine, host_machine};
+use builtins::machine::{build_machine, host_machine, target_machine};
 use builtins::meson::{Meson, meson};
 use builtins::option::{BuildOption, OptionType, get_option, option};
 use builtins::project::{add_project_arguments, project};
@@ -38,6 +47,8 @@ use builtins::variable::{get_variable, is_variable, set_variable};
 use builtins::{array as builtin_array, dict as builtin_dict, string as builtin_string};
 
 pub mod error;
+#[cfg(test)]
+mod test_utils;
 
 pub use error::InterpreterError;
 use error::{ErrorContext as _, bail_runtime_error, bail_type_error};
@@ -48,7 +59,7 @@ pub enum Value {
     Integer(i64),
     Boolean(bool),
     Array(Vec<Value>),
-    Dict(HashMap<String, Value>),
+    Dict(Dict<Value>),
     None,
     Object(Rc<RefCell<dyn MesonObject>>),
 }
@@ -128,7 +139,7 @@ impl Value {
         }
     }
 
-    fn as_dict(&self) -> Result<&HashMap<String, Value>, InterpreterError> {
+    fn as_dict(&self) -> Result<&Dict<Value>, InterpreterError> {
         match self {
             Value::Dict(d) => Ok(d),
             _ => bail_type_error!("Expected a dict, found {:?}", self),
@@ -213,6 +224,13 @@ pub fn borrow_downcast<'a, T: MesonObject>(
     }
 }
 
+/// The source text currently being interpreted, used to resolve error locations
+#[derive(Default)]
+struct Source {
+    path: Option<Path>,
+    text: String,
+}
+
 pub struct Interpreter {
     variables: HashMap<String, Value>,
     options: HashMap<String, BuildOption>,
@@ -220,9 +238,13 @@ pub struct Interpreter {
     continue_flag: bool,
     meson: Rc<RefCell<Meson>>,
     current_dir: Path,
+    src_dir: Path,
     build_dir: Path,
     os: Rc<dyn Runtime>,
     steps: Rc<dyn Builder>,
+    source: Rc<Source>,
+    machine_files: Rc<MachineFiles>,
+    check_cache: Rc<RefCell<CheckCache>>,
 }
 
 impl Interpreter {
@@ -231,6 +253,7 @@ impl Interpreter {
         steps: Rc<dyn Builder>,
         src_dir: Path,
         build_dir: Path,
+        machine_files: MachineFiles,
     ) -> Result<Self, InterpreterError> {
         let meson = meson();
         let meson = Rc::new(RefCell::new(meson));
@@ -241,10 +264,14 @@ impl Interpreter {
             break_flag: false,
             continue_flag: false,
             meson,
-            current_dir: src_dir,
+            current_dir: src_dir.clone(),
+            src_dir,
             build_dir,
             os,
             steps,
+            source: Default::default(),
+            machine_files: Rc::new(machine_files),
+            check_cache: Default::default(),
         };
 
         // Initialize built-in variables
@@ -273,7 +300,7 @@ impl Interpreter {
         // Target machine (when building a compiler, the machine the compiler will generate code for)
         self.variables.insert(
             "target_machine".to_string(),
-            host_machine(self)?.into_object(),
+            target_machine(self)?.into_object(),
         );
 
         // File system object
@@ -283,7 +310,10 @@ impl Interpreter {
         Ok(())
     }
 
-    pub fn interpret(&mut self, statements: Vec<Statement>) -> Result<(), InterpreterError> {
+    pub fn interpret(
+        &mut self,
+        statements: Vec<Spanned<Statement>>,
+    ) -> Result<(), InterpreterError> {
         for statement in statements {
             self.execute_statement(statement)?;
 
@@ -301,16 +331,50 @@ impl Interpreter {
             .with_context_runtime(|| format!("Failed to read file {file_path:?}"))?;
         let contents = String::from_utf8(contents)
             .with_context_runtime(|| format!("File is not utf-8 encoded: {file_path:?}"))?;
-        self.interpret_string(&contents)
+        // Report paths relative to the source root
+        let path = file_path
+            .strip_prefix(&self.src_dir)
+            .unwrap_or_else(|| file_path.clone());
+        self.interpret_source(Some(path), contents)
     }
 
     pub fn interpret_string(&mut self, contents: &str) -> Result<(), InterpreterError> {
-        let statements =
-            crate::parser::parse_meson_file(contents).context_runtime("Failed to parse")?;
-        self.interpret(statements)
+        self.interpret_source(None, contents.into())
     }
 
-    fn execute_statement(&mut self, statement: Statement) -> Result<(), InterpreterError> {
+    fn interpret_source(
+        &mut self,
+        path: Option<Path>,
+        text: String,
+    ) -> Result<(), InterpreterError> {
+        let statements = crate::parser::parse_meson_file(&text).map_err(|err| match &path {
+            Some(path) => InterpreterError::ParseError(err.with_file(path.clone())),
+            None => InterpreterError::ParseError(err),
+        })?;
+
+        let source = core::mem::replace(&mut self.source, Rc::new(Source { path, text }));
+        let res = self.interpret(statements);
+        self.source = source;
+
+        res
+    }
+
+    /// Resolves a span in the current source to a location for error reporting
+    fn locate(&self, span: Span) -> Location {
+        let location = Location::new(&self.source.text, span);
+        match &self.source.path {
+            Some(path) => location.with_file(path.clone()),
+            None => location,
+        }
+    }
+
+    fn execute_statement(&mut self, statement: Spanned<Statement>) -> Result<(), InterpreterError> {
+        let Spanned { node, span } = statement;
+        self.execute_statement_node(node)
+            .map_err(|err| err.in_statement(|| self.locate(span)))
+    }
+
+    fn execute_statement_node(&mut self, statement: Statement) -> Result<(), InterpreterError> {
         match statement {
             Statement::Assignment(name, value) => {
                 let evaluated = self.evaluate_value(value)?;
@@ -330,12 +394,18 @@ impl Interpreter {
             }
             Statement::If(condition, then_branch, elif_branches, else_branch) => {
                 let cond_value = self.evaluate_value(condition)?;
-                if cond_value.coerce_boolean() {
+                if cond_value.is_disabler() {
+                    // A disabled condition skips the whole statement, else included
+                } else if cond_value.coerce_boolean() {
                     self.execute_block(then_branch)?;
                 } else {
                     let mut executed = false;
                     for (elif_cond, elif_body) in elif_branches {
                         let elif_value = self.evaluate_value(elif_cond)?;
+                        if elif_value.is_disabler() {
+                            executed = true;
+                            break;
+                        }
                         if elif_value.coerce_boolean() {
                             self.execute_block(elif_body)?;
                             executed = true;
@@ -347,43 +417,54 @@ impl Interpreter {
                     }
                 }
             }
-            Statement::Foreach(var, iterable, body) => {
+            Statement::Foreach(vars, iterable, body) => {
                 let iter_value = self.evaluate_value(iterable)?;
-                match iter_value {
-                    Value::Array(items) => {
-                        for item in items {
-                            self.variables.insert(var.clone(), item);
-                            self.execute_block(body.clone())?;
-
-                            if self.break_flag {
-                                self.break_flag = false;
-                                break;
-                            }
-                            if self.continue_flag {
-                                self.continue_flag = false;
-                                continue;
-                            }
-                        }
+                if iter_value.is_disabler() {
+                    // Like an `if`, a disabled loop runs none of its body
+                    return Ok(());
+                }
+                let items: Vec<Vec<Value>> = match (iter_value, vars.len()) {
+                    (Value::Array(items), 1) => items.into_iter().map(|item| vec![item]).collect(),
+                    (Value::String(s), 1) => s
+                        .chars()
+                        .map(|ch| vec![Value::String(ch.to_string())])
+                        .collect(),
+                    (Value::Dict(dict), 2) => dict
+                        .into_iter()
+                        .map(|(key, value)| vec![Value::String(key), value])
+                        .collect(),
+                    (Value::Array(_), n) => {
+                        bail_type_error!("Foreach on array does not unpack, got {n} loop variables")
                     }
-                    Value::String(s) => {
-                        for ch in s.chars() {
-                            self.variables
-                                .insert(var.clone(), Value::String(ch.to_string()));
-                            self.execute_block(body.clone())?;
-
-                            if self.break_flag {
-                                self.break_flag = false;
-                                break;
-                            }
-                            if self.continue_flag {
-                                self.continue_flag = false;
-                                continue;
-                            }
-                        }
+                    (Value::String(_), n) => {
+                        bail_type_error!(
+                            "Foreach on string does not unpack, got {n} loop variables"
+                        )
+                    }
+                    (Value::Dict(_), _) => {
+                        bail_type_error!(
+                            "Foreach on dict unpacks key and value, expected 2 loop variables"
+                        )
                     }
                     _ => {
                         bail_type_error!("Cannot iterate over non-iterable");
                     }
+                };
+
+                for values in items {
+                    for (var, value) in vars.iter().zip(values) {
+                        self.variables.insert(var.clone(), value);
+                    }
+                    self.execute_block(body.clone())?;
+
+                    if self.break_flag {
+                        self.break_flag = false;
+                        break;
+                    }
+                    if self.continue_flag {
+                        self.continue_flag = false;
+                        continue;
+                    }
                 }
             }
             Statement::Break => {
@@ -396,7 +477,10 @@ impl Interpreter {
         Ok(())
     }
 
-    fn execute_block(&mut self, statements: Vec<Statement>) -> Result<(), InterpreterError> {
+    fn execute_block(
+        &mut self,
+        statements: Vec<Spanned<Statement>>,
+    ) -> Result<(), InterpreterError> {
         for statement in statements {
             self.execute_statement(statement)?;
             if self.break_flag || self.continue_flag {
@@ -420,7 +504,7 @@ impl Interpreter {
                 Ok(Value::Array(evaluated))
             }
             AstValue::Dict(dict) => {
-                let mut evaluated = HashMap::new();
+                let mut evaluated = Dict::default();
                 for (k, v) in dict {
                     evaluated.insert(k, self.evaluate_value(v)?);
                 }
@@ -431,28 +515,41 @@ impl Interpreter {
                 .get(&name)
                 .cloned()
                 .ok_or(InterpreterError::UndefinedVariable(name)),
-            AstValue::FunctionCall(name, args, kwargs) => self.call_function(&name, args, kwargs),
-            AstValue::MethodCall(object, method, args, kwargs) => {
+            AstValue::FunctionCall(name, args, kwargs, span) => {
+                self.call_function(&name, args, kwargs, span)
+            }
+            AstValue::MethodCall(object, method, args, kwargs, span) => {
                 let obj = self.evaluate_value(*object)?;
-                self.call_method(obj, &method, args, kwargs)
+                self.call_method(obj, &method, args, kwargs, span)
             }
             AstValue::BinaryOp(left, op, right) => {
                 let left_val = self.evaluate_value(*left)?;
                 let right_val = self.evaluate_value(*right)?;
+                if left_val.is_disabler() || right_val.is_disabler() {
+                    return Ok(Disabler.into_object());
+                }
                 self.apply_binary_op(left_val, op, right_val)
             }
             AstValue::UnaryOp(op, expr) => {
                 let val = self.evaluate_value(*expr)?;
+                if val.is_disabler() {
+                    return Ok(val);
+                }
                 self.apply_unary_op(op, val)
             }
             AstValue::Subscript(object, index) => {
                 let obj = self.evaluate_value(*object)?;
                 let idx = self.evaluate_value(*index)?;
+                if obj.is_disabler() || idx.is_disabler() {
+                    return Ok(Disabler.into_object());
+                }
                 self.subscript(obj, idx)
             }
             AstValue::TernaryOp(condition, true_val, false_val) => {
                 let cond = self.evaluate_value(*condition)?;
-                if cond.coerce_boolean() {
+                if cond.is_disabler() {
+                    Ok(cond)
+                } else if cond.coerce_boolean() {
                     self.evaluate_value(*true_val)
                 } else {
                     self.evaluate_value(*false_val)
@@ -466,6 +563,7 @@ impl Interpreter {
         name: &str,
         args: Vec<AstValue>,
         kwargs: HashMap<String, AstValue>,
+        span: Span,
     ) -> Result<Value, InterpreterError> {
         // Evaluate arguments
         let mut eval_args = Vec::new();
@@ -478,8 +576,16 @@ impl Interpreter {
             eval_kwargs.insert(k, self.evaluate_value(v)?);
         }
 
+        // A disabler argument disables the call, except for the functions
+        // that inspect or store values as they are
+        if !matches!(name, "is_disabler" | "get_variable" | "set_variable")
+            && is_disabled(&eval_args, &eval_kwargs)
+        {
+            return Ok(Disabler.into_object());
+        }
+
         // Built-in functions
-        match name {
+        let result = match name {
             "project" => project(eval_args, eval_kwargs, self),
             "option" => option(eval_args, eval_kwargs, self),
             "get_option" => get_option(eval_args, eval_kwargs, self),
@@ -498,17 +604,26 @@ impl Interpreter {
             "environment" => environment(eval_args, eval_kwargs, self),
             "join_paths" => join_paths(eval_args, eval_kwargs, self),
             "static_library" => static_library(eval_args, eval_kwargs, self),
+            "shared_library" => shared_library(eval_args, eval_kwargs, self),
+            "both_libraries" => both_libraries(eval_args, eval_kwargs, self),
+            "library" => library(eval_args, eval_kwargs, self),
             "executable" => executable(eval_args, eval_kwargs, self),
             "custom_target" => custom_target(eval_args, eval_kwargs, self),
             "test" => test(eval_args, eval_kwargs, self),
             "find_program" => find_program(eval_args, eval_kwargs, self),
+            "dependency" => dependency(eval_args, eval_kwargs, self),
+            "declare_dependency" => declare_dependency(eval_args, eval_kwargs, self),
             "install_headers" => install_headers(eval_args, eval_kwargs, self),
             "assert" => assert(eval_args, eval_kwargs, self),
             "message" => message(eval_args, eval_kwargs, self),
             "error" => error_fn(eval_args, eval_kwargs, self),
             "warning" => warning(eval_args, eval_kwargs, self),
+            "disabler" => disabler(eval_args, eval_kwargs, self),
+            "is_disabler" => is_disabler(eval_args, eval_kwargs, self),
             _ => Err(InterpreterError::UndefinedFunction(name.into())),
-        }
+        };
+
+        result.map_err(|err| err.in_call(name, || self.locate(span)))
     }
 
     fn call_method(
@@ -517,6 +632,7 @@ impl Interpreter {
         method: &str,
         args: Vec<AstValue>,
         kwargs: HashMap<String, AstValue>,
+        span: Span,
     ) -> Result<Value, InterpreterError> {
         // Evaluate arguments
         let mut eval_args = Vec::new();
@@ -529,6 +645,22 @@ impl Interpreter {
             eval_kwargs.insert(k, self.evaluate_value(v)?);
         }
 
+        // Methods of a disabler, or with a disabler argument, are disabled
+        if (object.is_disabler() && method != "found") || is_disabled(&eval_args, &eval_kwargs) {
+            return Ok(Disabler.into_object());
+        }
+
+        self.dispatch_method(object, method, eval_args, eval_kwargs)
+            .map_err(|err| err.in_call(method, || self.locate(span)))
+    }
+
+    fn dispatch_method(
+        &mut self,
+        object: Value,
+        method: &str,
+        eval_args: Vec<Value>,
+        eval_kwargs: HashMap<String, Value>,
+    ) -> Result<Value, InterpreterError> {
         match object {
             Value::String(ref s) => match method {
                 "format" => builtin_string::format(s, eval_args, eval_kwargs, self),
@@ -751,7 +883,21 @@ impl Interpreter {
     }
 
     pub fn get_option(&self, name: &str) -> Option<Value> {
-        self.options.get(name).map(|opt| opt.value.cloned())
+        let value = self.options.get(name)?.value.cloned();
+
+        // `auto_features` overrides the value of every feature left on auto
+        if let Some(feature) = Feature::from_value(&value)
+            && feature.state == FeatureState::Auto
+            && let Some(auto_features) = self
+                .options
+                .get("auto_features")
+                .and_then(|opt| Feature::from_value(&opt.value))
+        {
+            let state = auto_features.state;
+            return Some(Feature { state, ..feature }.into_object());
+        }
+
+        Some(value)
     }
 
     pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), InterpreterError> {
@@ -762,51 +908,190 @@ impl Interpreter {
             );
         };
 
-        match option.r#type {
-            OptionType::Boolean => {
-                let bool_value = value
-                    .parse()
-                    .context_runtime("Invalid value '{value}' for boolean option '{name}'")?;
-                option.value = Value::Boolean(bool_value);
-            }
-            OptionType::Integer(min, max) => {
-                let int_value: i64 = value
-                    .parse()
-                    .context_runtime("Invalid value '{value}' for integer option '{name}'")?;
-                option.value = Value::Integer(int_value.clamp(min, max));
-            }
-            OptionType::String(ref choices) => {
-                let value = value.into();
-                let valid = choices.is_empty() || choices.contains(&value);
-                if !valid {
-                    bail_runtime_error!(
-                        "Invalid value '{value}' for string option '{name}', allowed values are: {choices:?}"
-                    );
-                }
-                option.value = Value::String(value);
-            }
-            OptionType::Array(ref choices) => {
-                // TODO: check the actual behavior of this
-                let values = value
-                    .split(',')
-                    .map(str::trim)
-                    .map(String::from)
-                    .collect::<Vec<_>>();
-
-                let valid = values
-                    .iter()
-                    .all(|v| choices.is_empty() || choices.contains(v));
+        option.value = option.r#type.parse(name, value)?;
 
-                if !valid {
-                    bail_runtime_error!(
-                        "Invalid value '{value}' for array option '{name}', allowed values are: {choices:?}"
-                    );
-                }
-                let values = values.into_iter().map(Value::String).collect::<Vec<_>>();
-                option.value = Value::Array(values);
+        Ok(())
+    }
+
+    /// Replaces the compiler check cache with one saved by [`Self::save_check_cache`]
+    pub fn load_check_cache(&mut self, data: &[u8]) {
+        self.check_cache = Rc::new(RefCell::new(CheckCache::load(data)));
+    }
+
+    /// Serializes the compiler check cache, if checks ran since it was loaded
+    pub fn save_check_cache(&self) -> Option<String> {
+        self.check_cache.borrow_mut().save()
+    }
+
+    /// Sets options from a section of the machine files, such as
+    /// `[built-in options]`. The cross file takes precedence over the native
+    /// file.
+    pub fn set_machine_file_options(&mut self, section: &str) -> Result<(), InterpreterError> {
+        let machine_files = self.machine_files.clone();
+        let sections = [&machine_files.native, &machine_files.cross]
+            .into_iter()
+            .flatten()
+            .filter_map(|file| file.section(section));
+
+        for (name, value) in sections.flatten() {
+            let MachineValue::Array(items) = value else {
+                self.set_option(name, &value.coerce_string())?;
+                continue;
+            };
+
+            let Some(option) = self.options.get_mut(name) else {
+                bail_runtime_error!("Option '{name}' from [{section}] is not defined");
+            };
+            if !matches!(option.r#type, OptionType::Array(_)) {
+                bail_runtime_error!("Option '{name}' from [{section}] is not an array option");
             }
+            let value = items
+                .iter()
+                .map(|item| Value::String(item.coerce_string()))
+                .collect();
+            let value = Value::Array(value);
+            option.r#type.validate(name, &value)?;
+            option.value = value;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::{Harness, MockRuntime};
+    use super::*;
+
+    #[test]
+    fn test_disabler_propagation() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run(
+            r#"
+d = disabler()
+call = join_paths('a', d)
+kwarg_call = include_directories('inc', is_system: d)
+method = d.length()
+method_arg = 'a'.startswith(d)
+op = 1 + d
+negated = not d
+subscript = [1, 2][d]
+found = d.found()
+message(d)
+"#,
+        )
+        .unwrap();
+
+        for name in [
+            "call",
+            "kwarg_call",
+            "method",
+            "method_arg",
+            "op",
+            "negated",
+            "subscript",
+        ] {
+            assert!(h.get(name).is_disabler(), "{name} is not a disabler");
+        }
+        assert_eq!(h.get("found"), Value::Boolean(false));
+        assert!(h.os.printed.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_is_disabler() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run("a = is_disabler(disabler())\nb = is_disabler('x')\n")
+            .unwrap();
+        assert_eq!(h.get("a"), Value::Boolean(true));
+        assert_eq!(h.get("b"), Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_if_disabler() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run(
+            r#"
+r = 'none'
+if disabler()
+  r = 'then'
+else
+  r = 'else'
+endif
+e = 'none'
+if false
+  e = 'then'
+elif disabler()
+  e = 'elif'
+else
+  e = 'else'
+endif
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("r"), Value::String("none".into()));
+        assert_eq!(h.get("e"), Value::String("none".into()));
+    }
+
+    #[test]
+    fn test_foreach_disabler() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run("n = 0\nforeach x : disabler()\n  n += 1\nendforeach\n")
+            .unwrap();
+        assert_eq!(h.get("n"), Value::Integer(0));
+    }
+
+    #[test]
+    fn test_foreach() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run(
+            r#"
+pairs = []
+foreach k, v : {'z': 1, 'a': 2, 'm': 3}
+  pairs += '@0@=@1@'.format(k, v)
+endforeach
+items = []
+foreach x : ['b', 'a']
+  items += x
+endforeach
+"#,
+        )
+        .unwrap();
+        let strings = |items: &[&str]| {
+            Value::Array(items.iter().map(|s| Value::String(s.to_string())).collect())
+        };
+        assert_eq!(h.get("pairs"), strings(&["z=1", "a=2", "m=3"]));
+        assert_eq!(h.get("items"), strings(&["b", "a"]));
+
+        let err = h.run("foreach a, b : [1, 2]\nendforeach\n").unwrap_err();
+        let err = err.root().to_string();
+        assert!(
+            err.contains("Foreach on array does not unpack, got 2"),
+            "{err}"
+        );
+
+        let err = h.run("foreach a, b : 'ab'\nendforeach\n").unwrap_err();
+        let err = err.root().to_string();
+        assert!(err.contains("Foreach on string does not unpack"), "{err}");
+
+        let err = h.run("foreach k : {'a': 1}\nendforeach\n").unwrap_err();
+        let err = err.root().to_string();
+        assert!(err.contains("expected 2 loop variables"), "{err}");
+    }
+
+    #[test]
+    fn test_dict_order() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run(
+            r#"
+d = {'z': 1, 'a': 2, 'm': 3}
+keys = d.keys()
+values = d.values()
+"#,
+        )
+        .unwrap();
+        let keys = ["z", "a", "m"].map(|k| Value::String(k.into()));
+        assert_eq!(h.get("keys"), Value::Array(keys.into()));
+        let values = [1, 2, 3].map(Value::Integer);
+        assert_eq!(h.get("values"), Value::Array(values.into()));
+    }
+}
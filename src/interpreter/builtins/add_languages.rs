@@ -4,6 +4,9 @@ use alloc::vec::Vec;
 
 use hashbrown::HashMap;
 
+use super::compiler::{Language, add_compiler};
+use super::feature::{Requirement, requirement};
+use crate::interpreter::builtins::utils::flatten;
 use crate::interpreter::error::ErrorContext as _;
 use crate::interpreter::{Interpreter, InterpreterError, Value};
 
@@ -12,26 +15,110 @@ pub fn add_languages(
     kwargs: HashMap<String, Value>,
     interp: &mut Interpreter,
 ) -> Result<Value, InterpreterError> {
-    let lang = args
-        .first()
-        .context_type("First argument to add_languages must be a string")?
-        .as_string()
-        .context_type("First argument to add_languages must be a string")?;
-
-    let required = kwargs
-        .get("required")
+    let required = match requirement(&kwargs)? {
+        Requirement::Disabled => return Ok(Value::Boolean(false)),
+        requirement => requirement == Requirement::Required,
+    };
+
+    let native = kwargs
+        .get("native")
         .map(Value::as_boolean)
         .transpose()
-        .context_type("'required' keyword argument must be of type bool")?
-        .unwrap_or(false);
+        .context_type("The 'native' keyword argument must be a boolean")?;
+
+    // Without `native:` the languages are added for both machines, but only
+    // the host compilers are required
+    let found = match native {
+        Some(native) => add_languages_impl(&args, required, native, interp)?,
+        None => {
+            add_languages_impl(&args, false, true, interp)?;
+            add_languages_impl(&args, required, false, interp)?
+        }
+    };
+
+    Ok(Value::Boolean(found))
+}
+
+/// Adds the compilers of the given languages for one machine, returning
+/// whether all of them were found
+pub(super) fn add_languages_impl(
+    langs: &[Value],
+    required: bool,
+    native: bool,
+    interp: &Interpreter,
+) -> Result<bool, InterpreterError> {
+    let mut found = true;
+    for lang in flatten(langs) {
+        let lang = lang
+            .as_string()
+            .context_type("Expected languages to be strings")?;
+
+        let added = lang
+            .parse::<Language>()
+            .and_then(|lang| add_compiler(interp, lang, native));
+        if required {
+            added
+                .as_ref()
+                .with_context_runtime(|| format!("No compiler found for language: {lang}"))?;
+        }
+        found &= added.is_ok();
+    }
+    Ok(found)
+}
 
-    let compiler = interp.os.get_compiler(lang);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_utils::{Harness, MockRuntime, output};
 
-    if required {
-        compiler
-            .as_ref()
-            .with_context_runtime(|| format!("No compiler found for language: {lang}"))?;
+    fn harness() -> Harness {
+        let mut h = Harness::new(MockRuntime::new(|_| Ok(output(0, ""))));
+        h.run("project('t', 'c')").unwrap();
+        h
     }
 
-    Ok(Value::Boolean(compiler.is_ok()))
+    fn compiler_error(h: &mut Harness, code: &str) -> String {
+        h.run(code).unwrap_err().root().to_string()
+    }
+
+    #[test]
+    fn test_add_languages_native() {
+        let mut h = harness();
+        h.run("found = add_languages('cpp', native: true)").unwrap();
+        assert_eq!(h.get("found"), Value::Boolean(true));
+        h.run("meson.get_compiler('cpp', native: true)").unwrap();
+        let err = compiler_error(&mut h, "meson.get_compiler('cpp')");
+        assert!(
+            err.contains(
+                "Tried to access compiler for language \"cpp\", not specified for host machine"
+            ),
+            "{err}"
+        );
+
+        h.run("add_languages('objc', native: false)").unwrap();
+        h.run("meson.get_compiler('objc')").unwrap();
+        let err = compiler_error(&mut h, "meson.get_compiler('objc', native: true)");
+        assert!(err.contains("not specified for build machine"), "{err}");
+
+        // Without `native:` the language is added for both machines
+        h.run("add_languages('objcpp')").unwrap();
+        h.run("meson.get_compiler('objcpp')").unwrap();
+        h.run("meson.get_compiler('objcpp', native: true)").unwrap();
+    }
+
+    #[test]
+    fn test_get_compiler_not_added() {
+        let mut h = harness();
+        let err = compiler_error(&mut h, "meson.get_compiler('cpp')");
+        assert!(
+            err.contains(
+                "Tried to access compiler for language \"cpp\", not specified for host machine"
+            ),
+            "{err}"
+        );
+
+        // The per-language options exist whether or not the language is added
+        h.run("link_args = get_option('nasm_link_args')").unwrap();
+        assert_eq!(h.get("link_args"), Value::Array(Vec::new()));
+    }
 }
@@ -5,28 +5,52 @@ use alloc::{format, vec};
 use hashbrown::HashMap;
 
 use crate::interpreter::builtins::builtin_impl;
+use crate::interpreter::builtins::dependency::{Dependency, dependencies_impl};
 use crate::interpreter::builtins::files::{File, files_impl};
-use crate::interpreter::builtins::include_directories::IncludeDirectories;
+use crate::interpreter::builtins::include_directories::include_dirs_impl;
+use crate::interpreter::builtins::machine::host_machine;
 use crate::interpreter::builtins::utils::flatten;
-use crate::interpreter::error::ErrorContext;
+use crate::interpreter::error::{ErrorContext, bail_runtime_error, bail_type_error};
 use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
 use crate::path::Path;
 
+/// The kind of file a build target produces
 #[derive(Debug, Clone, PartialEq, Copy)]
-enum TargetType {
+pub enum TargetType {
     StaticLibrary,
+    SharedLibrary,
     Executable,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BuildTarget {
     pub name: String,
+    pub target_type: TargetType,
     pub filename: Path,
     pub sources: Vec<Path>,
     pub install: bool,
     pub include_dirs: Vec<Path>,
     pub install_dir: Path,
     pub flags: Vec<String>,
+    /// The name the linker records in a shared library, which is the
+    /// install name on Darwin
+    pub soname: Option<String>,
+    /// Other names of a shared library, to be created as symlinks to
+    /// `filename`
+    pub aliases: Vec<Path>,
+    /// The compatibility and current version of a shared library on Darwin
+    pub darwin_versions: Option<(String, String)>,
+    /// The libraries to link against, including those that static libraries
+    /// in the list link against themselves, each before its own libraries
+    pub link_with: Vec<BuildTarget>,
+    /// The static libraries whose objects are all linked in
+    pub link_whole: Vec<BuildTarget>,
+    pub link_args: Vec<String>,
+    /// Files that the target has to be relinked after they change
+    pub link_depends: Vec<Path>,
+    /// The dependencies found for the target, whose arguments, include
+    /// directories, sources and libraries are already merged into it
+    pub dependencies: Vec<Dependency>,
 }
 
 impl BuildTarget {
@@ -77,6 +101,97 @@ impl MesonObject for BuildTarget {
     builtin_impl!(extract_objects, extract_all_objects, full_path);
 }
 
+/// Which library of `both_libraries()` stands in for both, as chosen by the
+/// `default_both_libraries` option
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Preferred {
+    Shared,
+    Static,
+    /// The one matching the kind of target it is linked into
+    Auto,
+}
+
+/// The result of `both_libraries()`, which acts as the shared library
+/// unless the static one is preferred
+#[derive(Debug, Clone, PartialEq)]
+pub struct BothLibraries {
+    pub static_lib: BuildTarget,
+    pub shared_lib: BuildTarget,
+    preferred: Preferred,
+}
+
+impl MesonObject for BothLibraries {
+    builtin_impl!(
+        get_static_lib,
+        get_shared_lib,
+        extract_objects,
+        extract_all_objects,
+        full_path
+    );
+}
+
+impl BothLibraries {
+    fn preferred(&self) -> &BuildTarget {
+        match self.preferred {
+            Preferred::Static => &self.static_lib,
+            Preferred::Shared | Preferred::Auto => &self.shared_lib,
+        }
+    }
+
+    /// The library to link into a target of the given type
+    fn for_linking(&self, target_type: Option<TargetType>) -> &BuildTarget {
+        match (self.preferred, target_type) {
+            (Preferred::Auto, Some(TargetType::StaticLibrary)) => &self.static_lib,
+            _ => self.preferred(),
+        }
+    }
+
+    fn get_static_lib(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(self.static_lib.clone().into_object())
+    }
+
+    fn get_shared_lib(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(self.shared_lib.clone().into_object())
+    }
+
+    fn extract_objects(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        self.preferred().extract_objects(args, kwargs, interp)
+    }
+
+    fn extract_all_objects(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        self.preferred().extract_all_objects(args, kwargs, interp)
+    }
+
+    fn full_path(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        self.preferred().full_path(args, kwargs, interp)
+    }
+}
+
 pub fn static_library(
     args: Vec<Value>,
     kwargs: HashMap<String, Value>,
@@ -85,6 +200,52 @@ pub fn static_library(
     add_target_impl(TargetType::StaticLibrary, args, kwargs, interp)
 }
 
+pub fn shared_library(
+    args: Vec<Value>,
+    kwargs: HashMap<String, Value>,
+    interp: &mut Interpreter,
+) -> Result<Value, InterpreterError> {
+    add_target_impl(TargetType::SharedLibrary, args, kwargs, interp)
+}
+
+pub fn both_libraries(
+    args: Vec<Value>,
+    kwargs: HashMap<String, Value>,
+    interp: &mut Interpreter,
+) -> Result<Value, InterpreterError> {
+    let preferred = match interp.get_option("default_both_libraries") {
+        Some(Value::String(s)) if s == "static" => Preferred::Static,
+        Some(Value::String(s)) if s == "auto" => Preferred::Auto,
+        _ => Preferred::Shared,
+    };
+
+    let static_lib = target_impl(TargetType::StaticLibrary, &args, &kwargs, interp)?;
+    let shared_lib = target_impl(TargetType::SharedLibrary, &args, &kwargs, interp)?;
+    interp.steps.build_static_library(&static_lib);
+    interp.steps.build_shared_library(&shared_lib);
+
+    let both = BothLibraries {
+        static_lib,
+        shared_lib,
+        preferred,
+    };
+    Ok(both.into_object())
+}
+
+/// Builds a shared library, a static library or both, as chosen by the
+/// `default_library` option
+pub fn library(
+    args: Vec<Value>,
+    kwargs: HashMap<String, Value>,
+    interp: &mut Interpreter,
+) -> Result<Value, InterpreterError> {
+    match interp.get_option("default_library") {
+        Some(Value::String(s)) if s == "static" => static_library(args, kwargs, interp),
+        Some(Value::String(s)) if s == "both" => both_libraries(args, kwargs, interp),
+        _ => shared_library(args, kwargs, interp),
+    }
+}
+
 pub fn executable(
     args: Vec<Value>,
     kwargs: HashMap<String, Value>,
@@ -109,6 +270,23 @@ fn add_target_impl(
     kwargs: HashMap<String, Value>,
     interp: &mut Interpreter,
 ) -> Result<Value, InterpreterError> {
+    let target = target_impl(target_type, &args, &kwargs, interp)?;
+
+    match target_type {
+        TargetType::StaticLibrary => interp.steps.build_static_library(&target),
+        TargetType::SharedLibrary => interp.steps.build_shared_library(&target),
+        TargetType::Executable => interp.steps.build_executable(&target),
+    };
+
+    Ok(target.into_object())
+}
+
+fn target_impl(
+    target_type: TargetType,
+    args: &[Value],
+    kwargs: &HashMap<String, Value>,
+    interp: &Interpreter,
+) -> Result<BuildTarget, InterpreterError> {
     let Some(Value::String(name)) = args.first() else {
         return Err(InterpreterError::TypeError(
             "First argument must be a string (name)".into(),
@@ -155,11 +333,16 @@ fn add_target_impl(
         .transpose()
         .context_type("Expected 'install_dir' keyword argument to be a string")?;
 
+    let system = host_machine(interp)?.system;
+    // DLLs are found next to the executables that load them
+    let dll = matches!(system.as_str(), "windows" | "cygwin");
+
     let install_dir = match install_dir {
         Some(dir) => Some(Path::from(dir)),
         None => match target_type {
             TargetType::StaticLibrary => get_dir(interp, "libdir")?,
-            TargetType::Executable => get_dir(interp, "bindir")?,
+            TargetType::SharedLibrary if !dll => get_dir(interp, "libdir")?,
+            TargetType::SharedLibrary | TargetType::Executable => get_dir(interp, "bindir")?,
         },
     }
     .map(Path::from)
@@ -172,27 +355,10 @@ fn add_target_impl(
 
     let implicit_include_dirs = [interp.build_dir.clone(), interp.current_dir.clone()];
 
-    let include_dirs = kwargs
-        .get("include_directories")
-        .cloned()
-        .unwrap_or(Value::Array(vec![]));
+    let mut include_dirs = include_dirs_impl(kwargs.get("include_directories"), interp)?;
+    include_dirs.extend(implicit_include_dirs);
 
-    let include_dirs = flatten([include_dirs].as_slice())
-        .flat_map(|v| {
-            if let Ok(s) = v.as_string() {
-                vec![Ok(Path::from(interp.current_dir.join(s)))]
-            } else if let Ok(inc) = v.as_object::<IncludeDirectories>() {
-                inc.dirs.iter().map(|f| Ok(f.clone())).collect::<Vec<_>>()
-            } else {
-                vec![Err(InterpreterError::TypeError(
-                    "Expected elements of 'include_directories' to be strings or include_directories objects".into(),
-                ))]
-            }
-        })
-        .chain(implicit_include_dirs.into_iter().map(Result::Ok))
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let flags = kwargs
+    let mut flags = kwargs
         .get("c_args")
         .map(Value::as_array)
         .transpose()
@@ -206,28 +372,304 @@ fn add_target_impl(
     let mut sources = sources;
     sources.extend(objects);
 
+    let mut link_args = flatten(&kwargs.get("link_args"))
+        .map(|v| v.as_string().map(String::from))
+        .collect::<Result<Vec<_>, _>>()
+        .context_type("Expected elements of 'link_args' to be strings")?;
+
+    let mut direct_link_with = link_targets(kwargs, "link_with", Some(target_type))?;
+    let mut link_whole = link_targets(kwargs, "link_whole", Some(target_type))?;
+
+    let dependencies = dependencies_impl(kwargs)?;
+    for dep in &dependencies {
+        flags.extend(dep.compile_args.iter().cloned());
+        include_dirs.extend(dep.include_dirs.iter().cloned());
+        sources.extend(dep.sources.iter().cloned());
+        link_args.extend(dep.link_args.iter().cloned());
+        direct_link_with.extend(dep.link_with.iter().cloned());
+        link_whole.extend(dep.link_whole.iter().cloned());
+    }
+
+    let mut link_with = Vec::new();
+    for lib in &direct_link_with {
+        add_link_target(&mut link_with, &mut link_args, lib);
+    }
+    for lib in &link_whole {
+        if lib.target_type != TargetType::StaticLibrary {
+            bail_runtime_error!(
+                "Can only link_whole static libraries, but '{}' is not one",
+                lib.name
+            );
+        }
+        // The objects are linked in, but not what the archive depends on
+        link_args.extend(dependency_link_args(lib));
+        for dep in &lib.link_with {
+            add_link_target(&mut link_with, &mut link_args, dep);
+        }
+    }
+
+    let link_depends = files_impl(&kwargs.get("link_depends"), interp)?
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+
+    let mut soname = None;
+    let mut aliases = Vec::new();
+    let mut darwin_versions = None;
     let filename = match target_type {
         TargetType::StaticLibrary => format!("lib{name}.a"),
+        TargetType::SharedLibrary => {
+            let version = library_version(kwargs, "version")?;
+            let soversion = library_version(kwargs, "soversion")?.or_else(|| {
+                version
+                    .as_ref()
+                    .and_then(|v| v.split('.').next())
+                    .map(String::from)
+            });
+            if system == "darwin" {
+                darwin_versions = get_darwin_versions(kwargs, soversion.as_deref())?;
+            }
+
+            let names =
+                shared_library_names(name, &system, version.as_deref(), soversion.as_deref());
+            soname = names.soname;
+            aliases = names.aliases.into_iter().map(Path::from).collect();
+            names.filename
+        }
         TargetType::Executable => name.clone(),
     };
     let filename = Path::from(filename);
 
-    let lib = BuildTarget {
+    Ok(BuildTarget {
         name: name.clone(),
+        target_type,
         filename,
         sources,
         install,
         include_dirs,
         install_dir,
         flags,
+        soname,
+        aliases,
+        darwin_versions,
+        link_with,
+        link_whole,
+        link_args,
+        link_depends,
+        dependencies,
+    })
+}
+
+/// Reads the libraries of a `link_with:` or `link_whole:` keyword argument,
+/// picking the one of `both_libraries()` to link into a target of the given
+/// type, if it is known. `link_whole:` always takes the static library, the
+/// only one whose objects can be linked in.
+pub(super) fn link_targets(
+    kwargs: &HashMap<String, Value>,
+    key: &str,
+    target_type: Option<TargetType>,
+) -> Result<Vec<BuildTarget>, InterpreterError> {
+    let whole = key == "link_whole";
+    flatten(&kwargs.get(key))
+        .map(|v| {
+            if whole && let Ok(both) = v.as_object::<BothLibraries>() {
+                return Ok(both.static_lib.clone());
+            }
+            library_target(v, target_type)?
+                .with_context_type(|| format!("Expected elements of '{key}' to be libraries"))
+        })
+        .collect()
+}
+
+/// Returns the library a value refers to, picking one of `both_libraries()`
+/// for a target of the given type, or `None` if the value is not a target
+pub(super) fn library_target(
+    value: &Value,
+    target_type: Option<TargetType>,
+) -> Result<Option<BuildTarget>, InterpreterError> {
+    let lib = if let Ok(target) = value.as_object::<BuildTarget>() {
+        target.clone()
+    } else if let Ok(both) = value.as_object::<BothLibraries>() {
+        both.for_linking(target_type).clone()
+    } else {
+        return Ok(None);
     };
 
-    match target_type {
-        TargetType::StaticLibrary => interp.steps.build_static_library(&lib),
-        TargetType::Executable => interp.steps.build_executable(&lib),
+    if lib.target_type == TargetType::Executable {
+        bail_runtime_error!("Link target '{}' is not a library", lib.name);
+    }
+    Ok(Some(lib))
+}
+
+/// Adds a library to link against, along with the libraries and link
+/// arguments of its dependencies if it is a static library, since an archive
+/// does not record them. A library already in the list moves to the end, so
+/// that it still comes after every library needing it.
+fn add_link_target(
+    link_with: &mut Vec<BuildTarget>,
+    link_args: &mut Vec<String>,
+    lib: &BuildTarget,
+) {
+    let seen = match link_with.iter().position(|t| t.filename == lib.filename) {
+        Some(i) => {
+            link_with.remove(i);
+            true
+        }
+        None => false,
     };
+    link_with.push(lib.clone());
+
+    if lib.target_type == TargetType::StaticLibrary {
+        if !seen {
+            link_args.extend(dependency_link_args(lib));
+        }
+        for dep in &lib.link_with {
+            add_link_target(link_with, link_args, dep);
+        }
+    }
+}
+
+fn dependency_link_args(lib: &BuildTarget) -> impl Iterator<Item = String> + '_ {
+    let deps = lib.dependencies.iter();
+    deps.flat_map(|dep| dep.link_args.iter().cloned())
+}
 
-    Ok(lib.into_object())
+/// The file names of a shared library on the host system
+#[derive(Debug, PartialEq)]
+struct SharedLibraryNames {
+    filename: String,
+    soname: Option<String>,
+    aliases: Vec<String>,
+}
+
+fn shared_library_names(
+    name: &str,
+    system: &str,
+    version: Option<&str>,
+    soversion: Option<&str>,
+) -> SharedLibraryNames {
+    match system {
+        "windows" | "cygwin" => {
+            let prefix = if system == "cygwin" { "cyg" } else { "lib" };
+            let filename = match soversion {
+                Some(soversion) => format!("{prefix}{name}-{soversion}.dll"),
+                None => format!("{prefix}{name}.dll"),
+            };
+            SharedLibraryNames {
+                filename,
+                soname: None,
+                aliases: vec![],
+            }
+        }
+        "darwin" => {
+            let Some(soversion) = soversion else {
+                let filename = format!("lib{name}.dylib");
+                return SharedLibraryNames {
+                    soname: Some(format!("@rpath/{filename}")),
+                    filename,
+                    aliases: vec![],
+                };
+            };
+            let filename = format!("lib{name}.{soversion}.dylib");
+            SharedLibraryNames {
+                soname: Some(format!("@rpath/{filename}")),
+                filename,
+                aliases: vec![format!("lib{name}.dylib")],
+            }
+        }
+        _ => {
+            let soname = match soversion {
+                Some(soversion) => format!("lib{name}.so.{soversion}"),
+                None => format!("lib{name}.so"),
+            };
+            let filename = match version {
+                Some(version) => format!("lib{name}.so.{version}"),
+                None => soname.clone(),
+            };
+
+            // The soname is what programs load, and the unversioned name is
+            // what the linker looks for
+            let mut aliases = vec![];
+            if soname != filename {
+                aliases.push(soname.clone());
+            }
+            if soversion.is_some() {
+                aliases.push(format!("lib{name}.so"));
+            }
+            SharedLibraryNames {
+                filename,
+                soname: Some(soname),
+                aliases,
+            }
+        }
+    }
+}
+
+/// Whether a version is one to three dot separated numbers
+fn is_numeric_version(version: &str) -> bool {
+    let parts = version.split('.').collect::<Vec<_>>();
+    parts.len() <= 3
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Reads the `version:` or `soversion:` keyword argument of a shared library
+fn library_version(
+    kwargs: &HashMap<String, Value>,
+    key: &str,
+) -> Result<Option<String>, InterpreterError> {
+    let version = match kwargs.get(key) {
+        None | Some(Value::None) => return Ok(None),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Integer(i)) => i.to_string(),
+        Some(_) => bail_type_error!("Expected '{key}' keyword argument to be a string"),
+    };
+
+    if !is_numeric_version(&version) {
+        bail_runtime_error!(
+            "Invalid shared library {key} \"{version}\", must be of the form X.Y.Z where X, Y and Z are numbers, and Y and Z are optional"
+        );
+    }
+    Ok(Some(version))
+}
+
+/// Reads the `darwin_versions:` keyword argument, which is one or two
+/// versions, defaulting to the soversion for both
+fn get_darwin_versions(
+    kwargs: &HashMap<String, Value>,
+    soversion: Option<&str>,
+) -> Result<Option<(String, String)>, InterpreterError> {
+    let Some(value) = kwargs.get("darwin_versions") else {
+        return Ok(soversion.map(|v| (v.into(), v.into())));
+    };
+
+    let versions = flatten(core::slice::from_ref(value))
+        .map(|v| match v {
+            Value::String(s) => Ok(s.clone()),
+            Value::Integer(i) => Ok(i.to_string()),
+            _ => bail_type_error!("Expected 'darwin_versions' to be strings or integers"),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (compatibility, current) = match versions.as_slice() {
+        [version] => (version.clone(), version.clone()),
+        [compatibility, current] => (compatibility.clone(), current.clone()),
+        _ => bail_runtime_error!("'darwin_versions' must have one or two versions"),
+    };
+
+    for version in [&compatibility, &current] {
+        let fits = version
+            .split('.')
+            .zip([65535, 255, 255])
+            .all(|(part, max)| part.parse::<u32>().is_ok_and(|n| n <= max));
+        if !is_numeric_version(version) || !fits {
+            bail_runtime_error!(
+                "Invalid darwin version \"{version}\", must be of the form X.Y.Z where X is at most 65535 and Y and Z at most 255"
+            );
+        }
+    }
+    Ok(Some((compatibility, current)))
 }
 
 pub(super) fn get_dir(interp: &Interpreter, key: &str) -> Result<Option<Path>, InterpreterError> {
@@ -237,3 +679,240 @@ pub(super) fn get_dir(interp: &Interpreter, key: &str) -> Result<Option<Path>, I
         .transpose()
         .with_context_type(|| format!("Expected '{key}' option to be a string"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_utils::{Harness, MockRuntime, output};
+
+    #[test]
+    fn test_shared_library_names() {
+        let names = shared_library_names("foo", "linux", Some("1.2.3"), Some("1"));
+        assert_eq!(names.filename, "libfoo.so.1.2.3");
+        assert_eq!(names.soname.as_deref(), Some("libfoo.so.1"));
+        assert_eq!(names.aliases, vec!["libfoo.so.1", "libfoo.so"]);
+
+        let names = shared_library_names("foo", "linux", None, None);
+        assert_eq!(names.filename, "libfoo.so");
+        assert!(names.aliases.is_empty());
+
+        let names = shared_library_names("foo", "darwin", Some("1.2.3"), Some("1"));
+        assert_eq!(names.filename, "libfoo.1.dylib");
+        assert_eq!(names.soname.as_deref(), Some("@rpath/libfoo.1.dylib"));
+        assert_eq!(names.aliases, vec!["libfoo.dylib"]);
+
+        let names = shared_library_names("foo", "windows", None, Some("2"));
+        assert_eq!(names.filename, "libfoo-2.dll");
+        assert_eq!(names.soname, None);
+
+        let names = shared_library_names("foo", "cygwin", None, Some("2"));
+        assert_eq!(names.filename, "cygfoo-2.dll");
+        assert_eq!(names.soname, None);
+
+        let names = shared_library_names("foo", "cygwin", None, None);
+        assert_eq!(names.filename, "cygfoo.dll");
+    }
+
+    #[test]
+    fn test_transitive_link_with() {
+        let mut h = Harness::new(MockRuntime::new(|_| Ok(output(0, ""))));
+        h.run(
+            r#"
+project('t', 'c')
+m = declare_dependency(link_args: '-lm')
+c = shared_library('c')
+b = static_library('b', link_with: c, dependencies: m)
+a = static_library('a', link_with: [b, c])
+exe = executable('exe', link_with: [a, b])
+whole = executable('whole', link_whole: a)
+reversed = executable('reversed', link_with: [b, a])
+"#,
+        )
+        .unwrap();
+        let target = |name: &str| h.get(name).as_object::<BuildTarget>().unwrap().clone();
+        let names =
+            |targets: &[BuildTarget]| targets.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+
+        // Each static library comes before what it links against, once
+        let exe = target("exe");
+        assert_eq!(names(&exe.link_with), ["a", "b", "c"]);
+        assert!(exe.link_whole.is_empty());
+        assert_eq!(exe.link_args, ["-lm"]);
+
+        // A library named before one needing it still comes after it
+        let reversed = target("reversed");
+        assert_eq!(names(&reversed.link_with), ["a", "b", "c"]);
+        assert_eq!(reversed.link_args, ["-lm"]);
+
+        // The objects of a link_whole archive are linked in, but its own
+        // libraries are still linked against
+        let whole = target("whole");
+        assert_eq!(names(&whole.link_whole), ["a"]);
+        assert_eq!(names(&whole.link_with), ["b", "c"]);
+        assert_eq!(whole.link_args, ["-lm"]);
+
+        // Only the static library of both_libraries() can be linked whole
+        h.run("both = both_libraries('both')\nwhole_both = executable('wb', link_whole: both)\n")
+            .unwrap();
+        let whole_both = h.get("whole_both");
+        let whole_both = whole_both.as_object::<BuildTarget>().unwrap();
+        assert_eq!(whole_both.link_whole[0].filename.as_ref(), "libboth.a");
+        assert!(whole_both.link_with.is_empty());
+
+        let err = h.run("executable('bad', link_whole: c)").unwrap_err();
+        let err = err.root().to_string();
+        assert!(
+            err.contains("Can only link_whole static libraries"),
+            "{err}"
+        );
+    }
+
+    fn harness(system: &str) -> Harness {
+        let mut os = MockRuntime::new(|_| Ok(output(0, "")));
+        os.machine.system = system.into();
+        let mut h = Harness::new(os);
+        h.run("project('t', 'c')").unwrap();
+        h
+    }
+
+    /// The type and file name of each target given to the builder
+    fn built(h: &Harness) -> Vec<(TargetType, String)> {
+        let targets = h.steps.targets.borrow();
+        targets
+            .iter()
+            .map(|t| (t.target_type, t.filename.to_string()))
+            .collect()
+    }
+
+    fn error(h: &mut Harness, code: &str) -> String {
+        h.run(code).unwrap_err().root().to_string()
+    }
+
+    #[test]
+    fn test_library() {
+        let shared = (TargetType::SharedLibrary, "libfoo.so".to_string());
+        let stat = (TargetType::StaticLibrary, "libfoo.a".to_string());
+        for (default_library, expected) in [
+            ("shared", vec![shared.clone()]),
+            ("static", vec![stat.clone()]),
+            ("both", vec![stat.clone(), shared.clone()]),
+        ] {
+            let mut h = harness("linux");
+            h.interp
+                .set_option("default_library", default_library)
+                .unwrap();
+            h.run("lib = library('foo')").unwrap();
+            assert_eq!(built(&h), expected, "{default_library}");
+        }
+    }
+
+    #[test]
+    fn test_both_libraries() {
+        for (preferred, path) in [
+            ("shared", "/build/libfoo.so"),
+            ("static", "/build/libfoo.a"),
+            ("auto", "/build/libfoo.so"),
+        ] {
+            let mut h = harness("linux");
+            h.interp
+                .set_option("default_both_libraries", preferred)
+                .unwrap();
+            h.run(
+                r#"
+both = both_libraries('foo')
+path = both.full_path()
+static_path = both.get_static_lib().full_path()
+shared_path = both.get_shared_lib().full_path()
+"#,
+            )
+            .unwrap();
+            assert_eq!(h.get("path"), Value::String(path.into()), "{preferred}");
+            assert_eq!(
+                h.get("static_path"),
+                Value::String("/build/libfoo.a".into())
+            );
+            assert_eq!(
+                h.get("shared_path"),
+                Value::String("/build/libfoo.so".into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_shared_library() {
+        let mut h = harness("linux");
+        h.run("lib = shared_library('foo', version: '1.2.3')")
+            .unwrap();
+        let target = h.steps.targets.borrow()[0].clone();
+        assert_eq!(target.target_type, TargetType::SharedLibrary);
+        assert_eq!(target.filename.as_ref(), "libfoo.so.1.2.3");
+        assert_eq!(target.soname.as_deref(), Some("libfoo.so.1"));
+        assert_eq!(target.install_dir.as_ref(), "/usr/local/lib");
+
+        let err = error(&mut h, "shared_library('bar', version: '1.x')");
+        assert!(
+            err.contains("Invalid shared library version \"1.x\""),
+            "{err}"
+        );
+        let err = error(&mut h, "shared_library('bar', soversion: 'a')");
+        assert!(
+            err.contains("Invalid shared library soversion \"a\""),
+            "{err}"
+        );
+        let err = error(&mut h, "shared_library('bar', version: [1])");
+        assert!(err.contains("Expected 'version'"), "{err}");
+    }
+
+    #[test]
+    fn test_darwin_versions() {
+        let mut h = harness("darwin");
+        h.run(
+            r#"
+default = shared_library('default', version: '1.2.3')
+single = shared_library('single', darwin_versions: '2')
+pair = shared_library('pair', darwin_versions: [2, '3.1'])
+"#,
+        )
+        .unwrap();
+        let versions = |name: &str| {
+            let target = h.get(name);
+            let target = target.as_object::<BuildTarget>().unwrap();
+            target.darwin_versions.clone()
+        };
+        let pair = |a: &str, b: &str| Some((a.to_string(), b.to_string()));
+        assert_eq!(versions("default"), pair("1", "1"));
+        assert_eq!(versions("single"), pair("2", "2"));
+        assert_eq!(versions("pair"), pair("2", "3.1"));
+
+        let err = error(&mut h, "shared_library('big', darwin_versions: '65536')");
+        assert!(err.contains("Invalid darwin version \"65536\""), "{err}");
+        let err = error(&mut h, "shared_library('minor', darwin_versions: '1.256')");
+        assert!(err.contains("Invalid darwin version \"1.256\""), "{err}");
+        let err = error(
+            &mut h,
+            "shared_library('many', darwin_versions: ['1', '2', '3'])",
+        );
+        assert!(err.contains("one or two versions"), "{err}");
+    }
+
+    #[test]
+    fn test_dll_install_dir() {
+        for (system, filename) in [("windows", "libfoo-1.dll"), ("cygwin", "cygfoo-1.dll")] {
+            let mut h = harness(system);
+            h.run("shared_library('foo', soversion: '1')\nstatic_library('foo')\n")
+                .unwrap();
+            let targets = h.steps.targets.borrow();
+            assert_eq!(targets[0].filename.as_ref(), filename);
+            assert_eq!(
+                targets[0].install_dir.as_ref(),
+                "/usr/local/bin",
+                "{system}"
+            );
+            assert_eq!(
+                targets[1].install_dir.as_ref(),
+                "/usr/local/lib",
+                "{system}"
+            );
+        }
+    }
+}
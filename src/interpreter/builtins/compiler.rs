@@ -6,6 +6,11 @@ use core::iter;
 use hashbrown::HashMap;
 
 use super::builtin_impl;
+use super::dependency::dependencies_impl;
+use super::feature::{Requirement, requirement};
+use super::machine::can_run_host_binaries;
+use super::run_result::RunResult;
+use crate::interpreter::builtins::include_directories::IncludeDirectories;
 use crate::interpreter::builtins::utils::flatten;
 use crate::interpreter::error::ErrorContext as _;
 use crate::interpreter::{
@@ -14,14 +19,29 @@ use crate::interpreter::{
 use crate::path::Path;
 use crate::runtime::{CompilerInfo, TryCompileOutput};
 
+pub(crate) mod cache;
+mod language;
+mod linker;
+
+use cache::CheckKey;
+pub use language::Language;
+use linker::Linker;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Compiler {
-    lang: String,
+    lang: Language,
     command: Path,
     flags: Vec<String>,
+    /// Flags only passed when a check links
+    link_flags: Vec<String>,
+    /// The output of `--version`, which identifies the compiler in the check
+    /// cache
+    version: String,
+    linker: Linker,
 }
 
 const DELIMITER: &str = r#""MESON_DELIMITER""#;
+const UNDEFINED: &str = r#""MESON_GET_DEFINE_UNDEFINED_SENTINEL""#;
 
 impl MesonObject for Compiler {
     builtin_impl!(
@@ -30,12 +50,26 @@ impl MesonObject for Compiler {
         cmd_array,
         has_argument,
         get_supported_arguments,
+        get_supported_link_arguments,
+        first_supported_argument,
+        first_supported_link_argument,
         has_function,
         has_link_argument,
         has_multi_link_arguments,
         symbols_have_underscore_prefix,
         compiles,
         links,
+        sizeof,
+        alignment,
+        compute_int,
+        get_define,
+        has_header,
+        check_header,
+        has_header_symbol,
+        has_member,
+        has_members,
+        has_type,
+        run,
     );
 }
 
@@ -47,7 +81,7 @@ impl Compiler {
         interp: &mut Interpreter,
     ) -> Result<Value, InterpreterError> {
         let code = include_str!("compiler/compiler_id.c");
-        let result = self.try_compile(&["-E"], &[], code, interp)?;
+        let result = self.try_compile(&["-E"], &[] as &[&str], code, interp)?;
         let output = String::from_utf8_lossy(&result.artifact);
         let suffix = output.rsplit_once(DELIMITER).map(|(_, s)| s.trim());
         match suffix {
@@ -64,8 +98,7 @@ impl Compiler {
         _kwargs: HashMap<String, Value>,
         _interp: &mut Interpreter,
     ) -> Result<Value, InterpreterError> {
-        // TODO: actually detect linker
-        Ok(Value::String("ld.lld".into()))
+        Ok(Value::String(self.linker.id().into()))
     }
 
     fn cmd_array(
@@ -90,12 +123,9 @@ impl Compiler {
         let Some(Value::String(argument)) = args.first() else {
             bail_type_error!("has_argument requires a string argument");
         };
-        let required = match kwargs.get("required") {
-            Some(Value::Boolean(val)) => *val,
-            None => false,
-            _ => {
-                bail_type_error!("The 'required' keyword argument must be a boolean");
-            }
+        let required = match requirement(&kwargs)? {
+            Requirement::Disabled => return Ok(Value::Boolean(false)),
+            requirement => requirement == Requirement::Required,
         };
 
         let result = self.try_compile(&["-c"], &[argument], "", interp)?;
@@ -114,23 +144,72 @@ impl Compiler {
         _kwargs: HashMap<String, Value>,
         interp: &mut Interpreter,
     ) -> Result<Value, InterpreterError> {
-        let args = flatten(&args)
-            .map(|v| {
-                v.as_string()
-                    .context_type("Expected arguments to be strings")
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        let supported = self.probe_arguments(&args, false, interp)?;
+        Ok(Value::Array(supported.map(Value::String).collect()))
+    }
+
+    fn get_supported_link_arguments(
+        &self,
+        args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let supported = self.probe_arguments(&args, true, interp)?;
+        Ok(Value::Array(supported.map(Value::String).collect()))
+    }
+
+    fn first_supported_argument(
+        &self,
+        args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        // Probing every argument in one batch costs about as much as probing
+        // one, so there is nothing to gain from stopping early
+        let first = self.probe_arguments(&args, false, interp)?.next();
+        Ok(Value::Array(first.into_iter().map(Value::String).collect()))
+    }
 
-        let args = args
+    fn first_supported_link_argument(
+        &self,
+        args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let first = self.probe_arguments(&args, true, interp)?.next();
+        Ok(Value::Array(first.into_iter().map(Value::String).collect()))
+    }
+
+    /// Checks each argument on its own, compiling or linking them all as one
+    /// batch, and returns the supported ones in order
+    fn probe_arguments(
+        &self,
+        args: &[Value],
+        link: bool,
+        interp: &Interpreter,
+    ) -> Result<impl Iterator<Item = String>, InterpreterError> {
+        let args = string_args(args)?;
+        let probes = args
+            .iter()
+            .map(|arg| self.probe(arg, link, interp))
+            .collect::<Vec<_>>();
+        let results = self.try_compile_many(&probes, interp)?;
+
+        Ok(args
             .into_iter()
-            .filter_map(|arg| match self.try_compile(&["-c"], &[arg], "", interp) {
-                Ok(TryCompileOutput { success, .. }) => success.then_some(Ok(arg)),
-                Err(e) => Some(Err(e)),
-            })
-            .map(|arg| arg.map(|v| Value::String(v.to_string())))
-            .collect::<Result<Vec<_>, _>>()?;
+            .zip(results)
+            .filter_map(|(arg, result)| result.success.then_some(arg)))
+    }
 
-        Ok(Value::Array(args))
+    /// The arguments and snippet checking whether `arg` is supported
+    fn probe(&self, arg: &str, link: bool, interp: &Interpreter) -> (Vec<String>, &'static str) {
+        match link {
+            true => (
+                self.command_args(self.linker.fatal_warnings(), &[arg], interp),
+                "int main() { return 0; }",
+            ),
+            false => (self.command_args(&["-c"], &[arg], interp), ""),
+        }
     }
 
     fn has_function(
@@ -143,7 +222,7 @@ impl Compiler {
             bail_type_error!("has_function requires a string argument");
         };
 
-        let extra_args = get_extra_args(&kwargs)?;
+        let extra_args = get_extra_args(&kwargs, interp)?;
 
         let code = format!("int main() {{ void *p = (void*)({function}); return 0; }}");
 
@@ -164,7 +243,9 @@ impl Compiler {
 
         let code = "int main() { return 0; }";
 
-        let supported = self.try_compile(&[], &[argument], code, interp)?.success;
+        let supported = self
+            .try_compile(self.linker.fatal_warnings(), &[argument], code, interp)?
+            .success;
 
         Ok(Value::Boolean(supported))
     }
@@ -184,7 +265,9 @@ impl Compiler {
 
         let code = "int main() { return 0; }";
 
-        let supported = self.try_compile(&[], &args, code, interp)?.success;
+        let supported = self
+            .try_compile(self.linker.fatal_warnings(), &args, code, interp)?
+            .success;
 
         Ok(Value::Boolean(supported))
     }
@@ -196,7 +279,7 @@ impl Compiler {
         interp: &mut Interpreter,
     ) -> Result<Value, InterpreterError> {
         let code = include_str!("compiler/underscore_prefix.c");
-        let result = self.try_compile(&["-E"], &[], code, interp)?;
+        let result = self.try_compile(&["-E"], &[] as &[&str], code, interp)?;
         let output = String::from_utf8_lossy(&result.artifact);
         let suffix = output.rsplit_once(DELIMITER).map(|(_, s)| s.trim());
         match suffix {
@@ -222,7 +305,7 @@ impl Compiler {
             bail_type_error!("compiles requires a string argument");
         };
 
-        let extra_args = get_extra_args(&kwargs)?;
+        let extra_args = get_extra_args(&kwargs, interp)?;
 
         let success = self
             .try_compile(&["-c"], &extra_args, code, interp)?
@@ -241,149 +324,1270 @@ impl Compiler {
             bail_type_error!("links requires a string argument");
         };
 
-        let extra_args = get_extra_args(&kwargs)?;
+        let mut extra_args = get_extra_args(&kwargs, interp)?;
+        extra_args.extend(get_link_args(&kwargs)?);
 
         let success = self.try_compile(&[], &extra_args, code, interp)?.success;
 
         Ok(Value::Boolean(success))
     }
 
+    fn sizeof(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(typename)) = args.first() else {
+            bail_type_error!("sizeof requires a string argument");
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        let extra_args = get_extra_args(&kwargs, interp)?;
+
+        if !self.has_type_impl(typename, &prefix, &extra_args, interp)? {
+            return Ok(Value::Integer(-1));
+        }
+
+        let expression = format!("sizeof({typename})");
+        let size = self.compute_int_impl(&expression, None, &prefix, &extra_args, interp)?;
+
+        Ok(Value::Integer(size))
+    }
+
+    fn alignment(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(typename)) = args.first() else {
+            bail_type_error!("alignment requires a string argument");
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        let extra_args = get_extra_args(&kwargs, interp)?;
+
+        if !self.has_type_impl(typename, &prefix, &extra_args, interp)? {
+            bail_runtime_error!("Cannot determine alignment of {typename}: type not found");
+        }
+
+        let expression = format!("offsetof(struct {{ char c; {typename} target; }}, target)");
+        let align = self.compute_int_impl(&expression, None, &prefix, &extra_args, interp)?;
+
+        Ok(Value::Integer(align))
+    }
+
+    fn compute_int(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(expression)) = args.first() else {
+            bail_type_error!("compute_int requires a string argument");
+        };
+
+        let int_kwarg = |name: &str| {
+            kwargs
+                .get(name)
+                .map(Value::as_integer)
+                .transpose()
+                .with_context_type(|| format!("The '{name}' keyword argument must be an integer"))
+        };
+        let (low, high, guess) = (int_kwarg("low")?, int_kwarg("high")?, int_kwarg("guess")?);
+
+        let bounds = match (low, high) {
+            (Some(low), Some(high)) => Some((low, high)),
+            (None, None) => None,
+            _ => bail_type_error!("compute_int requires both 'low' and 'high', or neither"),
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        let extra_args = get_extra_args(&kwargs, interp)?;
+
+        if let Some(guess) = guess {
+            let condition = format!("({expression}) == {guess}");
+            if self.compiles_int(&condition, &prefix, &extra_args, interp)? {
+                return Ok(Value::Integer(guess));
+            }
+        }
+
+        let value = self.compute_int_impl(expression, bounds, &prefix, &extra_args, interp)?;
+
+        Ok(Value::Integer(value))
+    }
+
+    fn get_define(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(define)) = args.first() else {
+            bail_type_error!("get_define requires a string argument");
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        let extra_args = get_extra_args(&kwargs, interp)?;
+
+        let code = format!(
+            "{prefix}\n#ifndef {define}\n# define {define} {UNDEFINED}\n#endif\n{DELIMITER} {define}\n"
+        );
+        let result = self.try_compile(&["-E"], &extra_args, &code, interp)?;
+        if !result.success {
+            bail_runtime_error!("Could not get define {define}");
+        }
+
+        let output = String::from_utf8_lossy(&result.artifact);
+        let value = output
+            .rsplit_once(DELIMITER)
+            .map(|(_, s)| s.trim())
+            .with_context_runtime(|| format!("Failed to find the value of define {define}"))?;
+
+        match value {
+            UNDEFINED => Ok(Value::String(String::new())),
+            value => Ok(Value::String(value.into())),
+        }
+    }
+
+    fn has_header(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(header)) = args.first() else {
+            bail_type_error!("has_header requires a string argument");
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        let code = format!(
+            "{prefix}\n#ifdef __has_include\n #if !__has_include(\"{header}\")\n  #error \"Header '{header}' could not be found\"\n #endif\n#else\n #include <{header}>\n#endif\n"
+        );
+
+        self.check(
+            "-E",
+            &code,
+            &kwargs,
+            || format!("Header '{header}'"),
+            interp,
+        )
+    }
+
+    fn check_header(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(header)) = args.first() else {
+            bail_type_error!("check_header requires a string argument");
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        let code = format!("{prefix}\n#include <{header}>\n");
+
+        self.check(
+            "-c",
+            &code,
+            &kwargs,
+            || format!("Header '{header}'"),
+            interp,
+        )
+    }
+
+    fn has_header_symbol(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let (Some(Value::String(header)), Some(Value::String(symbol))) =
+            (args.first(), args.get(1))
+        else {
+            bail_type_error!("has_header_symbol requires a header and a symbol argument");
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        // A macro is not a symbol, so only use the name if it is not defined
+        let code = format!(
+            "{prefix}\n#include <{header}>\nint main(void) {{\n#ifndef {symbol}\n    {symbol};\n#endif\n    return 0;\n}}\n"
+        );
+
+        let what = || format!("Symbol '{symbol}' in header '{header}'");
+        self.check("-c", &code, &kwargs, what, interp)
+    }
+
+    fn has_member(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        if args.len() != 2 {
+            bail_type_error!("has_member requires a type and a member argument");
+        }
+        self.has_members(args, kwargs, interp)
+    }
+
+    fn has_members(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some((typename, members)) = args.split_first() else {
+            bail_type_error!("has_members requires a type argument");
+        };
+        let typename = typename
+            .as_string()
+            .context_type("Expected the type name to be a string")?;
+        let members = flatten(members)
+            .map(|v| {
+                v.as_string()
+                    .context_type("Expected member names to be strings")
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if members.is_empty() {
+            bail_type_error!("has_members requires at least one member argument");
+        }
+
+        let prefix = get_prefix(&kwargs)?;
+        let accesses: String = members.iter().map(|m| format!("    foo.{m};\n")).collect();
+        let code = format!("{prefix}\nvoid bar(void) {{\n    {typename} foo;\n{accesses}}}\n");
+
+        let what = || format!("Members {members:?} of '{typename}'");
+        self.check("-c", &code, &kwargs, what, interp)
+    }
+
+    fn has_type(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(typename)) = args.first() else {
+            bail_type_error!("has_type requires a string argument");
+        };
+
+        let prefix = get_prefix(&kwargs)?;
+        let code = format!("{prefix}\nvoid bar(void) {{ sizeof({typename}); }}\n");
+
+        self.check(
+            "-c",
+            &code,
+            &kwargs,
+            || format!("Type '{typename}'"),
+            interp,
+        )
+    }
+
+    /// Links and runs a snippet, through the `exe_wrapper` of the cross file
+    /// if the host's binaries cannot run on the build machine
+    fn run(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let Some(Value::String(code)) = args.first() else {
+            bail_type_error!("run requires a string argument");
+        };
+
+        let wrapper = match can_run_host_binaries(interp)? {
+            true => None,
+            false => match interp.machine_files.binary("exe_wrapper", false) {
+                Some((wrapper, wrapper_args)) => Some((Path::from(wrapper), wrapper_args)),
+                None => bail_runtime_error!(
+                    "Can not run test applications in this cross environment: the cross file has no exe_wrapper"
+                ),
+            },
+        };
+
+        let mut extra_args = get_extra_args(&kwargs, interp)?;
+        extra_args.extend(get_link_args(&kwargs)?);
+
+        let outdir = interp
+            .os
+            .tempdir()
+            .context_runtime("Failed to create temporary directory")?;
+        let cmd_args = self.command_args(&[], &extra_args, interp);
+        let Some(exe) = self.compile_in(outdir.path(), &cmd_args, code, interp)? else {
+            return Ok(RunResult::not_compiled().into_object());
+        };
+
+        let output = match &wrapper {
+            Some((wrapper, wrapper_args)) => {
+                let args = wrapper_args
+                    .iter()
+                    .map(String::as_str)
+                    .chain([exe.as_ref()])
+                    .collect::<Vec<_>>();
+                interp.os.run_command(wrapper, &args)
+            }
+            None => interp.os.run_command(&exe, &[]),
+        }
+        .context_runtime("Failed to run the compiled program")?;
+
+        Ok(RunResult::from(output).into_object())
+    }
+
+    /// Runs a check in the given mode, honouring the `required:` keyword
+    /// argument: a required check that fails is an error, and a disabled
+    /// feature skips the check
+    fn check(
+        &self,
+        mode: &str,
+        code: &str,
+        kwargs: &HashMap<String, Value>,
+        what: impl FnOnce() -> String,
+        interp: &Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let required = match requirement(kwargs)? {
+            Requirement::Disabled => return Ok(Value::Boolean(false)),
+            requirement => requirement == Requirement::Required,
+        };
+
+        let extra_args = get_extra_args(kwargs, interp)?;
+        let found = self
+            .try_compile(&[mode], &extra_args, code, interp)?
+            .success;
+
+        if required && !found {
+            bail_runtime_error!("{} not found", what());
+        }
+
+        Ok(Value::Boolean(found))
+    }
+
+    fn has_type_impl(
+        &self,
+        typename: &str,
+        prefix: &str,
+        extra_args: &[String],
+        interp: &Interpreter,
+    ) -> Result<bool, InterpreterError> {
+        let code = format!("{prefix}\nvoid bar(void) {{ sizeof({typename}); }}\n");
+        Ok(self
+            .try_compile(&["-c"], extra_args, &code, interp)?
+            .success)
+    }
+
+    /// Whether a constant expression holds, checked at compile time so that
+    /// it works when cross compiling
+    fn compiles_int(
+        &self,
+        condition: &str,
+        prefix: &str,
+        extra_args: &[String],
+        interp: &Interpreter,
+    ) -> Result<bool, InterpreterError> {
+        let code = format!(
+            "{prefix}\n#include <stddef.h>\nint main(void) {{ static int a[1-2*!({condition})]; a[0]=0; return 0; }}\n"
+        );
+        Ok(self
+            .try_compile(&["-c"], extra_args, &code, interp)?
+            .success)
+    }
+
+    /// Finds the value of a constant expression by binary search, first
+    /// growing the bounds within the range of `int` unless they are given
+    fn compute_int_impl(
+        &self,
+        expression: &str,
+        bounds: Option<(i64, i64)>,
+        prefix: &str,
+        extra_args: &[String],
+        interp: &Interpreter,
+    ) -> Result<i64, InterpreterError> {
+        const MAXINT: i64 = i32::MAX as i64;
+        const MININT: i64 = i32::MIN as i64;
+
+        let holds = |condition: String| self.compiles_int(&condition, prefix, extra_args, interp);
+
+        let (mut low, mut high) = match bounds {
+            Some((low, high)) => {
+                if high < low {
+                    bail_runtime_error!("compute_int: high limit smaller than low limit");
+                }
+                if !holds(format!(
+                    "({expression}) <= {high} && ({expression}) >= {low}"
+                ))? {
+                    bail_runtime_error!("compute_int: value out of given range");
+                }
+                (low, high)
+            }
+            None if holds(format!("({expression}) >= 0"))? => {
+                let (mut low, mut cur) = (0, 0);
+                while holds(format!("({expression}) > {cur}"))? {
+                    low = cur + 1;
+                    if low > MAXINT {
+                        bail_runtime_error!("Cross-compile check overflowed");
+                    }
+                    cur = (cur * 2 + 1).min(MAXINT);
+                }
+                (low, cur)
+            }
+            None => {
+                let (mut high, mut cur) = (-1, -1);
+                while holds(format!("({expression}) < {cur}"))? {
+                    high = cur - 1;
+                    if high < MININT {
+                        bail_runtime_error!("Cross-compile check overflowed");
+                    }
+                    cur = (cur * 2).max(MININT);
+                }
+                (cur, high)
+            }
+        };
+
+        while low != high {
+            let cur = low + (high - low) / 2;
+            if holds(format!("({expression}) <= {cur}"))? {
+                high = cur;
+            } else {
+                low = cur + 1;
+            }
+        }
+
+        Ok(low)
+    }
+
     fn try_compile(
         &self,
         args: &[&str],
-        extra_args: &[&str],
+        extra_args: &[impl AsRef<str>],
         code: &str,
         interp: &Interpreter,
     ) -> Result<TryCompileOutput, InterpreterError> {
-        let meson = interp.meson.borrow();
-        let args = args.iter().copied();
-        let cmd_args = meson
-            .project_args
-            .get("c")
-            .map(Vec::as_slice)
-            .unwrap_or_default()
+        let cmd_args = self.command_args(args, extra_args, interp);
+        let mut results = self.try_compile_many(&[(cmd_args, code)], interp)?;
+        Ok(results.remove(0))
+    }
+
+    /// Compiles several snippets, each with its own arguments. Those that are
+    /// not cached are compiled as one batch through `Runtime::run_commands`,
+    /// which may run them in parallel.
+    fn try_compile_many(
+        &self,
+        probes: &[(Vec<String>, &str)],
+        interp: &Interpreter,
+    ) -> Result<Vec<TryCompileOutput>, InterpreterError> {
+        let keys = probes
             .iter()
-            .map(String::as_str);
+            .map(|(cmd_args, code)| {
+                CheckKey::new(self.command.as_ref(), &self.version, cmd_args, code)
+            })
+            .collect::<Vec<_>>();
 
-        let cmd_args = cmd_args.chain(args).chain(extra_args.iter().copied());
-        let cmd_args = cmd_args.chain(self.flags.iter().map(String::as_str));
+        let mut results = {
+            let cache = interp.check_cache.borrow();
+            keys.iter()
+                .map(|key| cache.get(key).cloned())
+                .collect::<Vec<_>>()
+        };
+
+        let misses = (0..probes.len())
+            .filter(|&i| results[i].is_none())
+            .collect::<Vec<_>>();
+        if misses.is_empty() {
+            return Ok(results.into_iter().flatten().collect());
+        }
 
         let outdir = interp
             .os
             .tempdir()
             .context_runtime("Failed to create temporary directory")?;
 
-        let out_path = outdir.path().join("output");
+        let commands = misses
+            .iter()
+            .map(|&i| {
+                let (cmd_args, code) = &probes[i];
+                self.prepare(outdir.path(), i, cmd_args, code, interp)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-        let input_filename = match self.lang.as_str() {
-            "c" => "input.c",
-            "cpp" => "input.cpp",
-            _ => {
-                return Err(InterpreterError::RuntimeError(format!(
-                    "Unsupported language: {}",
-                    self.lang
-                )));
-            }
+        let argvs = commands
+            .iter()
+            .map(|(argv, _)| argv.iter().map(String::as_str).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let batch = argvs
+            .iter()
+            .map(|argv| (&self.command, argv.as_slice()))
+            .collect::<Vec<_>>();
+        let outputs = interp.os.run_commands(&batch);
+
+        let mut cache = interp.check_cache.borrow_mut();
+        for ((&i, (_, out_path)), output) in misses.iter().zip(&commands).zip(outputs) {
+            let output = output.context_runtime("Failed to run compiler")?;
+
+            // Only preprocessor output is ever read back, so don't keep
+            // objects and executables around
+            let artifact = match probes[i].0.iter().any(|arg| arg == "-E") {
+                true => interp.os.read_file(out_path).unwrap_or_default(),
+                false => Vec::new(),
+            };
+            let result = TryCompileOutput {
+                success: output.returncode == 0,
+                artifact,
+            };
+
+            cache.insert(keys[i].clone(), result.clone());
+            results[i] = Some(result);
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// The arguments for compiling a snippet, without the input and output.
+    /// Unless `args` stops before linking, the link flags are included.
+    fn command_args(
+        &self,
+        args: &[&str],
+        extra_args: &[impl AsRef<str>],
+        interp: &Interpreter,
+    ) -> Vec<String> {
+        let meson = interp.meson.borrow();
+        let project_args = meson.project_args.get(self.lang.name()).map(Vec::as_slice);
+
+        let links = !args.iter().any(|arg| matches!(*arg, "-c" | "-E" | "-S"));
+        let link_flags = match links {
+            true => self.link_flags.as_slice(),
+            false => &[],
         };
-        let input = outdir.path().join(input_filename);
-        interp
-            .os
-            .write_file(&input, code.as_bytes())
-            .context_runtime("Failed to write temporary source file")?;
 
-        let cmd_args = cmd_args.chain([input.as_ref(), "-o", out_path.as_ref()]);
+        project_args
+            .unwrap_or_default()
+            .iter()
+            .map(String::as_str)
+            .chain(args.iter().copied())
+            .chain(extra_args.iter().map(AsRef::as_ref))
+            .chain(self.flags.iter().map(String::as_str))
+            .chain(link_flags.iter().map(String::as_str))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Compiles a snippet inside `dir`, returning the path of the output if
+    /// it compiled, which lives as long as the directory
+    fn compile_in(
+        &self,
+        dir: &Path,
+        cmd_args: &[String],
+        code: &str,
+        interp: &Interpreter,
+    ) -> Result<Option<Path>, InterpreterError> {
+        let (argv, out_path) = self.prepare(dir, 0, cmd_args, code, interp)?;
 
         let result = interp
             .os
-            .run_command(&self.command, &cmd_args.collect::<Vec<_>>())
+            .run_command(
+                &self.command,
+                &argv.iter().map(String::as_str).collect::<Vec<_>>(),
+            )
             .context_runtime("Failed to run compiler")?;
 
-        let artifact = interp.os.read_file(&out_path).unwrap_or_default();
+        Ok((result.returncode == 0).then_some(out_path))
+    }
 
-        let result = TryCompileOutput {
-            success: result.returncode == 0,
-            artifact,
-        };
+    /// Writes the `n`th snippet of a batch to `dir`, returning the compiler
+    /// arguments and the output path
+    fn prepare(
+        &self,
+        dir: &Path,
+        n: usize,
+        cmd_args: &[String],
+        code: &str,
+        interp: &Interpreter,
+    ) -> Result<(Vec<String>, Path), InterpreterError> {
+        let out_path = dir.join(format!("output{n}"));
+
+        let input = dir.join(format!("input{n}.{}", self.lang.suffix()));
+        interp
+            .os
+            .write_file(&input, code.as_bytes())
+            .context_runtime("Failed to write temporary source file")?;
+
+        let mut argv = cmd_args.to_vec();
+        argv.extend([input.to_string(), "-o".into(), out_path.to_string()]);
 
-        Ok(result)
+        Ok((argv, out_path))
     }
 }
 
-fn get_extra_args(kwargs: &HashMap<String, Value>) -> Result<Vec<&str>, InterpreterError> {
-    match kwargs.get("args") {
+/// The flattened arguments of the argument checking methods
+fn string_args(args: &[Value]) -> Result<Vec<String>, InterpreterError> {
+    flatten(args)
+        .map(|v| {
+            v.as_string()
+                .map(String::from)
+                .context_type("Expected arguments to be strings")
+        })
+        .collect()
+}
+
+/// The `prefix:` keyword argument, code put before every check
+fn get_prefix(kwargs: &HashMap<String, Value>) -> Result<String, InterpreterError> {
+    match kwargs.get("prefix") {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(Value::Array(arr)) => flatten(arr)
+            .map(|v| v.as_string())
+            .collect::<Result<Vec<_>, _>>()
+            .map(|lines| lines.join("\n"))
+            .context_type("The 'prefix' keyword argument must be a string or an array of strings"),
+        None => Ok(String::new()),
+        _ => bail_type_error!(
+            "The 'prefix' keyword argument must be a string or an array of strings"
+        ),
+    }
+}
+
+/// The arguments a check is compiled with: `args:`, plus `-I` for each of
+/// the `include_directories:`
+fn get_extra_args(
+    kwargs: &HashMap<String, Value>,
+    interp: &Interpreter,
+) -> Result<Vec<String>, InterpreterError> {
+    let mut extra_args = match kwargs.get("args") {
         Some(Value::Array(arr)) => flatten(arr)
             .map(|v| match v {
-                Value::String(s) => Ok(s.as_str()),
+                Value::String(s) => Ok(s.clone()),
                 _ => Err(InterpreterError::TypeError(
                     "The 'args' keyword argument must be an array of strings".into(),
                 )),
             })
-            .collect(),
-        None => Ok(Vec::new()),
-        _ => Err(InterpreterError::TypeError(
-            "The 'args' keyword argument must be an array of strings".into(),
-        )),
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+        _ => {
+            return Err(InterpreterError::TypeError(
+                "The 'args' keyword argument must be an array of strings".into(),
+            ));
+        }
+    };
+
+    for dir in flatten(&kwargs.get("include_directories")) {
+        if let Ok(dir) = dir.as_string() {
+            extra_args.push(format!("-I{}", interp.current_dir.join(dir)));
+        } else if let Ok(inc) = dir.as_object::<IncludeDirectories>() {
+            extra_args.extend(inc.dirs.iter().map(|dir| format!("-I{dir}")));
+        } else {
+            bail_type_error!(
+                "Expected elements of 'include_directories' to be strings or include_directories objects"
+            );
+        }
     }
+
+    for dep in dependencies_impl(kwargs)? {
+        extra_args.extend(dep.compile_args);
+        extra_args.extend(dep.include_dirs.iter().map(|dir| format!("-I{dir}")));
+    }
+
+    Ok(extra_args)
 }
 
-fn get_compiler_argv0(interp: &mut Interpreter, lang: &str) -> Result<Path, InterpreterError> {
-    let compiler_info = interp
-        .os
-        .get_compiler(lang)
-        .with_context_runtime(|| format!("Failed to get compiler for language: {lang}"))?;
+/// The link arguments of the `dependencies:` keyword argument, for checks
+/// that link
+fn get_link_args(kwargs: &HashMap<String, Value>) -> Result<Vec<String>, InterpreterError> {
+    let deps = dependencies_impl(kwargs)?;
+    Ok(deps.into_iter().flat_map(|dep| dep.link_args).collect())
+}
+
+/// Looks up the compiler for a language, preferring the `[binaries]` of the
+/// machine files over the runtime's compiler
+fn compiler_info(
+    interp: &Interpreter,
+    lang: Language,
+    native: bool,
+) -> Result<CompilerInfo, InterpreterError> {
+    if let Some((bin, flags)) = interp.machine_files.binary(lang.name(), native) {
+        return Ok(CompilerInfo {
+            bin: Path::from(bin),
+            flags,
+        });
+    }
 
-    Ok(compiler_info.bin)
+    interp
+        .os
+        .get_compiler(lang.name())
+        .with_context_runtime(|| format!("Failed to get compiler for language: {lang}"))
 }
 
-fn get_compiler_flags(
-    interp: &mut Interpreter,
-    lang: &str,
+/// Reads the `<lang>_args` or `<lang>_link_args` option
+fn language_args(
+    interp: &Interpreter,
+    lang: Language,
+    kind: &str,
 ) -> Result<Vec<String>, InterpreterError> {
-    let mut flags = Vec::new();
+    let name = format!("{lang}_{kind}");
+    let Some(flags) = interp.options.get(&name) else {
+        return Ok(Vec::new());
+    };
 
-    if let Some(f) = interp.options.get(&format!("{lang}_args")) {
-        let f = f
-            .value
-            .as_array()
-            .context_type("Expected compiler flags option to be an array")?
-            .iter()
-            .map(|v| v.as_string().map(String::from))
-            .collect::<Result<Vec<String>, _>>()
-            .context_type("Expected compiler flags option to be an array of strings")?;
+    flags
+        .value
+        .as_array()
+        .with_context_type(|| format!("Expected option '{name}' to be an array"))?
+        .iter()
+        .map(|v| v.as_string().map(String::from))
+        .collect::<Result<Vec<String>, _>>()
+        .with_context_type(|| format!("Expected option '{name}' to be an array of strings"))
+}
 
-        flags.extend(f);
+/// Runs the compiler with `--version`, on every configure since the output
+/// tells whether cached checks still apply
+fn compiler_version(command: &Path, flags: &[String], interp: &Interpreter) -> String {
+    let mut argv = flags.iter().map(String::as_str).collect::<Vec<_>>();
+    argv.push("--version");
+    match interp.os.run_command(command, &argv) {
+        Ok(output) => output.stdout + &output.stderr,
+        Err(_) => String::new(),
     }
+}
 
-    if let Ok(CompilerInfo { flags: f, .. }) = interp.os.get_compiler(lang) {
-        flags.extend(f);
+/// Finds the compiler for a language and registers it for the host or build
+/// machine, so that `meson.get_compiler()` can return it
+pub fn add_compiler(
+    interp: &Interpreter,
+    lang: Language,
+    native: bool,
+) -> Result<(), InterpreterError> {
+    if interp
+        .meson
+        .borrow()
+        .compilers
+        .contains_key(&(lang, native))
+    {
+        return Ok(());
     }
 
-    Ok(flags)
+    let CompilerInfo { bin, flags } = compiler_info(interp, lang, native)?;
+
+    let mut all_flags = language_args(interp, lang, "args")?;
+    all_flags.extend(flags);
+    let link_flags = language_args(interp, lang, "link_args")?;
+
+    let version = compiler_version(&bin, &all_flags, interp);
+    let linker = Linker::detect(&bin, &all_flags, &version, interp);
+
+    let compiler = Compiler {
+        lang,
+        command: bin,
+        flags: all_flags,
+        link_flags,
+        version,
+        linker,
+    };
+    interp
+        .meson
+        .borrow_mut()
+        .compilers
+        .insert((lang, native), compiler);
+    Ok(())
 }
 
+/// Implements `meson.get_compiler()` on the compilers registered so far
 pub fn get_compiler(
+    compilers: &HashMap<(Language, bool), Compiler>,
     args: Vec<Value>,
-    _kwargs: HashMap<String, Value>,
-    interp: &mut Interpreter,
+    kwargs: HashMap<String, Value>,
 ) -> Result<Value, InterpreterError> {
     let lang = args
         .first()
         .context_type("Expected a string as the first argument")?
         .as_string()
         .context_type("Expected a string as the first argument")?;
+    let lang = lang.parse::<Language>()?;
+
+    let native = kwargs
+        .get("native")
+        .map(Value::as_boolean)
+        .transpose()
+        .context_type("The 'native' keyword argument must be a boolean")?
+        .unwrap_or(false);
+
+    let Some(compiler) = compilers.get(&(lang, native)) else {
+        let machine = if native { "build" } else { "host" };
+        bail_runtime_error!(
+            "Tried to access compiler for language \"{lang}\", not specified for {machine} machine"
+        );
+    };
+    Ok(compiler.clone().into_object())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use alloc::string::ToString as _;
+    use core::cell::{Cell, RefCell};
+
+    use super::*;
+    use crate::interpreter::test_utils::{Harness, Invocation, MockRuntime, output};
+    use crate::runtime::RunCommandOutput;
+
+    /// A compiler knowing the size and alignment of a few types and the
+    /// values of a few macros, which it reveals through the compile time
+    /// assertions of `compute_int`
+    fn mock_compiler(invocation: &Invocation) -> crate::runtime::Result<RunCommandOutput> {
+        const TYPES: &[(&str, i64, i64)] = &[("int", 4, 4), ("double", 8, 8)];
+        const DEFINES: &[(&str, i64)] = &[("ANSWER", 42), ("NEGATIVE", -37)];
+
+        let Some(source) = &invocation.source else {
+            return Ok(output(1, ""));
+        };
+
+        if invocation.args.contains(&"-E") {
+            let define = source.rsplit_once(DELIMITER).unwrap().1.trim();
+            let value = DEFINES
+                .iter()
+                .find(|(name, _)| *name == define)
+                .map_or(UNDEFINED.to_string(), |(_, value)| value.to_string());
+            return Ok(output(0, &format!("{DELIMITER} {value}\n")));
+        }
+
+        if let Some((_, rest)) = source.split_once("static int a[1-2*!(") {
+            let mut condition = rest.rsplit_once(")];").unwrap().0.to_string();
+            for (ty, size, align) in TYPES {
+                condition = condition.replace(&format!("(sizeof({ty}))"), &size.to_string());
+                let offset = format!("(offsetof(struct {{ char c; {ty} target; }}, target))");
+                condition = condition.replace(&offset, &align.to_string());
+            }
+            for (name, value) in DEFINES {
+                condition = condition.replace(&format!("({name})"), &value.to_string());
+            }
+            return Ok(output(if holds(&condition) { 0 } else { 1 }, ""));
+        }
+
+        let known = TYPES
+            .iter()
+            .any(|(ty, ..)| source.contains(&format!("sizeof({ty})")));
+        Ok(output(if known { 0 } else { 1 }, ""))
+    }
+
+    /// Evaluates comparisons of integers joined by `&&`
+    fn holds(condition: &str) -> bool {
+        condition.split("&&").all(|cmp| {
+            let [a, op, b] = cmp.split_whitespace().collect::<Vec<_>>()[..] else {
+                panic!("unexpected condition {condition}");
+            };
+            let (a, b) = (a.parse::<i64>().unwrap(), b.parse::<i64>().unwrap());
+            match op {
+                "==" => a == b,
+                "<" => a < b,
+                "<=" => a <= b,
+                ">" => a > b,
+                ">=" => a >= b,
+                _ => panic!("unexpected operator {op}"),
+            }
+        })
+    }
+
+    /// A compiler knowing a few headers with the symbols they declare, and
+    /// `struct stat` from `<sys/stat.h>`
+    fn header_compiler(invocation: &Invocation) -> crate::runtime::Result<RunCommandOutput> {
+        const HEADERS: &[(&str, &[&str])] =
+            &[("stdio.h", &["printf", "FILE"]), ("sys/stat.h", &["stat"])];
+        const MEMBERS: &[&str] = &["st_size", "st_mode"];
+
+        let Some(source) = &invocation.source else {
+            return Ok(output(1, ""));
+        };
+
+        let mut headers = Vec::new();
+        let mut found = true;
+        for line in source.lines().map(str::trim) {
+            let included = line
+                .strip_prefix("#include <")
+                .and_then(|rest| rest.strip_suffix('>'))
+                .or_else(|| {
+                    let rest = line.strip_prefix("#if !__has_include(\"")?;
+                    rest.strip_suffix("\")")
+                });
+            if let Some(header) = included {
+                match HEADERS.iter().find(|(name, _)| *name == header) {
+                    Some((_, symbols)) => headers.extend(symbols.iter()),
+                    None => found = false,
+                }
+            } else if let Some(member) = line
+                .strip_prefix("foo.")
+                .and_then(|rest| rest.strip_suffix(';'))
+            {
+                found &= MEMBERS.contains(&member);
+            } else if let Some(symbol) = line.strip_suffix(';')
+                && !line.contains(' ')
+            {
+                found &= headers.contains(&symbol);
+            } else if let Some(ty) = line
+                .strip_prefix("void bar(void) { sizeof(")
+                .and_then(|rest| rest.strip_suffix("); }"))
+            {
+                found &= ty == "int" || (ty == "struct stat" && headers.contains(&"stat"));
+            }
+        }
+        Ok(output(if found { 0 } else { 1 }, ""))
+    }
+
+    fn harness() -> Harness {
+        let mut h = Harness::new(MockRuntime::new(mock_compiler));
+        h.run("project('t', 'c')\ncc = meson.get_compiler('c')\n")
+            .unwrap();
+        h
+    }
+
+    #[test]
+    fn test_sizeof_alignment() {
+        let mut h = harness();
+        h.run(
+            r#"
+int_size = cc.sizeof('int')
+double_size = cc.sizeof('double')
+missing_size = cc.sizeof('struct missing')
+double_align = cc.alignment('double')
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("int_size"), Value::Integer(4));
+        assert_eq!(h.get("double_size"), Value::Integer(8));
+        assert_eq!(h.get("missing_size"), Value::Integer(-1));
+        assert_eq!(h.get("double_align"), Value::Integer(8));
+
+        let err = h.run("cc.alignment('struct missing')").unwrap_err();
+        let err = err.root().to_string();
+        assert!(err.contains("type not found"), "{err}");
+    }
+
+    #[test]
+    fn test_compute_int() {
+        let mut h = harness();
+        h.run(
+            r#"
+answer = cc.compute_int('ANSWER')
+negative = cc.compute_int('NEGATIVE')
+bounded = cc.compute_int('ANSWER', low: 40, high: 50)
+guessed = cc.compute_int('ANSWER', guess: 42)
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("answer"), Value::Integer(42));
+        assert_eq!(h.get("negative"), Value::Integer(-37));
+        assert_eq!(h.get("bounded"), Value::Integer(42));
+        assert_eq!(h.get("guessed"), Value::Integer(42));
 
-    let command = get_compiler_argv0(interp, lang).with_context_runtime(|| {
-        format!("Failed to determine compiler command for language: {lang}")
-    })?;
+        let err = h.run("cc.compute_int('ANSWER', low: 0, high: 10)");
+        let err = err.unwrap_err().root().to_string();
+        assert!(err.contains("value out of given range"), "{err}");
 
-    let flags = get_compiler_flags(interp, lang).with_context_runtime(|| {
-        format!("Failed to determine compiler flags for language: {lang}")
-    })?;
+        let err = h.run("cc.compute_int('ANSWER', low: 0)").unwrap_err();
+        let err = err.root().to_string();
+        assert!(err.contains("both 'low' and 'high'"), "{err}");
+    }
+
+    #[test]
+    fn test_get_define() {
+        let mut h = harness();
+        h.run("answer = cc.get_define('ANSWER')\nmissing = cc.get_define('MISSING')\n")
+            .unwrap();
+        assert_eq!(h.get("answer"), Value::String("42".into()));
+        assert_eq!(h.get("missing"), Value::String("".into()));
+    }
+
+    #[test]
+    fn test_header_checks() {
+        let mut h = Harness::new(MockRuntime::new(header_compiler));
+        h.run("project('t', 'c')\ncc = meson.get_compiler('c')\n")
+            .unwrap();
+        h.run(
+            r#"
+stat = '#include <sys/stat.h>'
+results = [
+  cc.has_header('stdio.h'),
+  cc.has_header('missing.h'),
+  cc.check_header('sys/stat.h'),
+  cc.check_header('missing.h'),
+  cc.has_header_symbol('stdio.h', 'printf'),
+  cc.has_header_symbol('stdio.h', 'stat'),
+  cc.has_member('struct stat', 'st_size', prefix: stat),
+  cc.has_members('struct stat', 'st_size', 'st_mode', prefix: stat),
+  cc.has_members('struct stat', 'st_size', 'st_missing', prefix: stat),
+  cc.has_type('struct stat', prefix: stat),
+  cc.has_type('struct stat'),
+  cc.has_header('missing.h', required: false),
+]
+"#,
+        )
+        .unwrap();
+
+        let expected = [
+            true, false, true, false, true, false, true, true, false, true, false, false,
+        ];
+        assert_eq!(
+            h.get("results"),
+            Value::Array(expected.map(Value::Boolean).to_vec())
+        );
+
+        let err = h.run("cc.has_header('missing.h', required: true)");
+        let err = err.unwrap_err().root().to_string();
+        assert!(err.contains("Header 'missing.h' not found"), "{err}");
+
+        let err = h.run("cc.has_header_symbol('stdio.h', 'stat', required: true)");
+        let err = err.unwrap_err().root().to_string();
+        assert!(
+            err.contains("Symbol 'stat' in header 'stdio.h' not found"),
+            "{err}"
+        );
+    }
+
+    /// Compiles anything but code containing `FAIL`, and runs programs
+    /// printing the command they were run with, exiting with 3
+    fn run_compiler(invocation: &Invocation) -> crate::runtime::Result<RunCommandOutput> {
+        match invocation.cmd {
+            "cc" => {
+                let fails = invocation
+                    .source
+                    .as_ref()
+                    .is_none_or(|source| source.contains("FAIL"));
+                Ok(output(if fails { 1 } else { 0 }, ""))
+            }
+            cmd => {
+                let argv = iter::once(&cmd).chain(invocation.args).copied();
+                Ok(output(3, &argv.collect::<Vec<_>>().join(" ")))
+            }
+        }
+    }
+
+    const RUN: &str = r#"
+project('t', 'c')
+cc = meson.get_compiler('c')
+r = cc.run('int main(void) { return 3; }')
+results = [r.compiled(), r.returncode(), r.stdout()]
+"#;
+
+    #[test]
+    fn test_run() {
+        let mut h = Harness::new(MockRuntime::new(run_compiler));
+        h.run(RUN).unwrap();
+        let results = h.get("results");
+        let results = results.as_array().unwrap();
+        assert_eq!(results[0], Value::Boolean(true));
+        assert_eq!(results[1], Value::Integer(3));
+        assert!(
+            results[2].as_string().unwrap().ends_with("/output0"),
+            "{results:?}"
+        );
+
+        h.run("f = cc.run('FAIL')\nfailed = [f.compiled(), f.returncode()]\n")
+            .unwrap();
+        assert_eq!(
+            h.get("failed"),
+            Value::Array(alloc::vec![Value::Boolean(false), Value::Integer(999)])
+        );
+    }
+
+    #[test]
+    fn test_run_exe_wrapper() {
+        let cross = r#"
+[binaries]
+c = 'cc'
+exe_wrapper = ['qemu-arm', '-L', '/sysroot']
+[properties]
+needs_exe_wrapper = true
+"#;
+        let os = MockRuntime::new(run_compiler);
+        let mut h = Harness::with_machine_files(os, Some(cross), None);
+        h.run(RUN).unwrap();
+        let results = h.get("results");
+        let stdout = results.as_array().unwrap()[2].as_string().unwrap();
+        assert!(stdout.starts_with("qemu-arm -L /sysroot /tmp/"), "{stdout}");
+
+        let cross = "[binaries]\nc = 'cc'\n[properties]\nneeds_exe_wrapper = true\n";
+        let os = MockRuntime::new(run_compiler);
+        let mut h = Harness::with_machine_files(os, Some(cross), None);
+        let err = h.run(RUN).unwrap_err().root().to_string();
+        assert!(err.contains("the cross file has no exe_wrapper"), "{err}");
+    }
+
+    #[test]
+    fn test_cache_follows_compiler_version() {
+        // Returns the number of checks compiled and the saved cache
+        let configure = |version: &'static str, cache: Option<&str>| {
+            let compiles = Rc::new(Cell::new(0));
+            let counter = compiles.clone();
+            let os = MockRuntime::new(move |invocation| {
+                if invocation.args.contains(&"--version") {
+                    return Ok(output(0, version));
+                }
+                if invocation.args.contains(&"-c") {
+                    counter.set(counter.get() + 1);
+                }
+                Ok(output(0, ""))
+            });
+
+            let mut h = Harness::new(os);
+            if let Some(cache) = cache {
+                h.interp.load_check_cache(cache.as_bytes());
+            }
+            h.run("project('t', 'c')\nmeson.get_compiler('c').has_argument('-Wall')\n")
+                .unwrap();
+            (compiles.get(), h.interp.save_check_cache())
+        };
+
+        let (compiles, saved) = configure("gcc 12.2.0", None);
+        assert_eq!(compiles, 1);
+        let saved = saved.unwrap();
+
+        let (compiles, _) = configure("gcc 12.2.0", Some(&saved));
+        assert_eq!(compiles, 0);
+
+        let (compiles, _) = configure("gcc 13.1.0", Some(&saved));
+        assert_eq!(compiles, 1);
+    }
+
+    #[test]
+    fn test_probe_arguments() {
+        const SUPPORTED: &[&str] = &["-fsecond", "-fthird"];
+
+        // The arguments probed, in order
+        let probed = Rc::new(RefCell::new(Vec::new()));
+        let recorder = probed.clone();
+        let os = MockRuntime::new(move |invocation| {
+            let Some(arg) = invocation.args.iter().find(|arg| arg.starts_with("-f")) else {
+                return Ok(output(0, ""));
+            };
+            recorder.borrow_mut().push(arg.to_string());
+            Ok(output(if SUPPORTED.contains(arg) { 0 } else { 1 }, ""))
+        });
+        let mut h = Harness::new(os);
+        h.run("project('t', 'c')\ncc = meson.get_compiler('c')\n")
+            .unwrap();
+        let args = "['-ffirst', '-fsecond', '-fthird', '-ffourth']";
+        let strings = |names: &[&str]| {
+            Value::Array(
+                names
+                    .iter()
+                    .map(|name| Value::String(name.to_string()))
+                    .collect(),
+            )
+        };
+        let all = ["-ffirst", "-fsecond", "-fthird", "-ffourth"];
+
+        // Every argument is probed as one batch
+        h.os.batches.borrow_mut().clear();
+        h.run(&format!("first = cc.first_supported_argument({args})"))
+            .unwrap();
+        assert_eq!(h.get("first"), strings(&["-fsecond"]));
+        assert_eq!(*probed.borrow(), all);
+        assert_eq!(*h.os.batches.borrow(), [4]);
+
+        // Then the results are cached
+        probed.borrow_mut().clear();
+        h.os.batches.borrow_mut().clear();
+        h.run(&format!("supported = cc.get_supported_arguments({args})"))
+            .unwrap();
+        assert_eq!(h.get("supported"), strings(&["-fsecond", "-fthird"]));
+        assert!(probed.borrow().is_empty());
+        assert!(h.os.batches.borrow().is_empty());
+
+        h.run(&format!(
+            "supported = cc.get_supported_link_arguments({args})"
+        ))
+        .unwrap();
+        assert_eq!(h.get("supported"), strings(&["-fsecond", "-fthird"]));
+        assert_eq!(*probed.borrow(), all);
+        assert_eq!(*h.os.batches.borrow(), [4]);
+
+        h.run(&format!("first = cc.first_supported_link_argument({args})"))
+            .unwrap();
+        assert_eq!(h.get("first"), strings(&["-fsecond"]));
+
+        h.run("first = cc.first_supported_argument(['-fnone'])")
+            .unwrap();
+        assert_eq!(h.get("first"), strings(&[]));
+    }
+
+    #[test]
+    fn test_get_linker_id() {
+        let mut h = Harness::new(MockRuntime::new(|invocation| {
+            match invocation.args.contains(&"-Wl,--version") {
+                true => Ok(output(0, "GNU ld (GNU Binutils) 2.40")),
+                false => Ok(output(0, "")),
+            }
+        }));
+        h.run("project('t', 'c')\nid = meson.get_compiler('c').get_linker_id()\n")
+            .unwrap();
+        assert_eq!(h.get("id"), Value::String("ld.bfd".into()));
+
+        // The linker is not detected when the compiler can't be run
+        let mut h = Harness::new(MockRuntime::default());
+        h.run("project('t', 'c')\nid = meson.get_compiler('c').get_linker_id()\n")
+            .unwrap();
+        assert_eq!(h.get("id"), Value::String("unknown".into()));
+    }
+
+    #[test]
+    fn test_language_arguments() {
+        // The arguments of each compile, without the input and output
+        let compiles = Rc::new(RefCell::new(Vec::new()));
+        let recorder = compiles.clone();
+        let os = MockRuntime::new(move |invocation| {
+            if invocation.source.is_some() {
+                let args = invocation.args.iter().map(|arg| arg.to_string());
+                recorder.borrow_mut().push(args.collect::<Vec<_>>());
+            }
+            Ok(output(0, ""))
+        });
+        let native = "[built-in options]\ncpp_args = ['-DCPP_ARG']\ncpp_link_args = ['-lcpp']\n";
+        let mut h = Harness::with_machine_files(os, None, Some(native));
+        h.run(
+            r#"
+project('t', 'c', 'cpp')
+add_project_arguments('-DC_PROJECT', language: 'c')
+add_project_arguments('-DCPP_PROJECT', language: 'cpp')
+add_project_arguments('-DBOTH', '-DMORE', language: ['c', 'cpp'])
+meson.get_compiler('c').compiles('int x;')
+cxx = meson.get_compiler('cpp')
+cxx.compiles('int y;')
+cxx.links('int main() { return 0; }')
+"#,
+        )
+        .unwrap();
+
+        let compiles = compiles.borrow();
+        let [c, cpp, cpp_link] = &compiles[..] else {
+            panic!("Expected three compiles, got {compiles:?}");
+        };
+        let flags = |args: &[String]| {
+            let (input, rest) = args[..args.len() - 2].split_last().unwrap();
+            (input.rsplit_once('.').unwrap().1.to_string(), rest.to_vec())
+        };
 
-    Ok(Compiler {
-        command,
-        flags,
-        lang: lang.into(),
+        // Project arguments are appended per language and come first
+        assert_eq!(
+            flags(c),
+            (
+                "c".into(),
+                ["-DC_PROJECT", "-DBOTH", "-DMORE", "-c"]
+                    .map(String::from)
+                    .into()
+            )
+        );
+        assert_eq!(
+            flags(cpp),
+            (
+                "cpp".into(),
+                ["-DCPP_PROJECT", "-DBOTH", "-DMORE", "-c", "-DCPP_ARG"]
+                    .map(String::from)
+                    .into()
+            )
+        );
+        // Only checks that link get the link arguments
+        assert_eq!(
+            flags(cpp_link),
+            (
+                "cpp".into(),
+                ["-DCPP_PROJECT", "-DBOTH", "-DMORE", "-DCPP_ARG", "-lcpp"]
+                    .map(String::from)
+                    .into()
+            )
+        );
     }
-    .into_object())
 }
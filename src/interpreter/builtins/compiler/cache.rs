@@ -0,0 +1,255 @@
+//! Memoised results of compiler checks
+//!
+//! Every check compiles a snippet in a fresh temporary directory, so the
+//! results are keyed on what determines the outcome: the compiler command and
+//! a hash of its `--version` output, which changes when the compiler is
+//! upgraded or replaced, its arguments in order (project arguments, mode,
+//! extra arguments and compiler flags) and a hash of the code. The cache can
+//! be written to the build directory to skip every check on a reconfigure
+//! with unchanged inputs.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::runtime::TryCompileOutput;
+
+/// The first line of a saved cache, bumped whenever the format changes
+const HEADER: &str = "picomeson compiler checks v2";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CheckKey {
+    command: String,
+    version_hash: u64,
+    args: Vec<String>,
+    code_hash: u64,
+}
+
+impl CheckKey {
+    pub fn new(command: &str, version: &str, args: &[String], code: &str) -> Self {
+        CheckKey {
+            command: command.into(),
+            version_hash: fnv1a(version.as_bytes()),
+            args: args.to_vec(),
+            code_hash: fnv1a(code.as_bytes()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CheckCache {
+    entries: HashMap<CheckKey, TryCompileOutput>,
+    /// Whether there are results that have not been saved
+    dirty: bool,
+}
+
+impl CheckCache {
+    pub fn get(&self, key: &CheckKey) -> Option<&TryCompileOutput> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, key: CheckKey, output: TryCompileOutput) {
+        self.entries.insert(key, output);
+        self.dirty = true;
+    }
+
+    /// Loads a saved cache, ignoring it if it is from another version or
+    /// damaged
+    pub fn load(data: &[u8]) -> Self {
+        let entries = core::str::from_utf8(data)
+            .ok()
+            .and_then(|text| {
+                let mut lines = text.lines();
+                if lines.next()? != HEADER {
+                    return None;
+                }
+                lines.map(parse_entry).collect::<Option<HashMap<_, _>>>()
+            })
+            .unwrap_or_default();
+
+        CheckCache {
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Serializes the cache if it changed since it was loaded, sorting the
+    /// entries so that the same results are always saved the same way
+    pub fn save(&mut self) -> Option<String> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+
+        let mut lines = self
+            .entries
+            .iter()
+            .map(|(key, output)| {
+                let args = key.args.iter().map(|arg| hex(arg.as_bytes()));
+                format!(
+                    "{} {:016x} {} {:016x} {} {}",
+                    hex(key.command.as_bytes()),
+                    key.version_hash,
+                    args.collect::<Vec<_>>().join(","),
+                    key.code_hash,
+                    output.success as u8,
+                    hex(&output.artifact),
+                )
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+
+        let mut out = String::from(HEADER);
+        out.push('\n');
+        for line in lines {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        Some(out)
+    }
+}
+
+/// Parses a `command version_hash args code_hash success artifact` line,
+/// where strings are hex encoded and the arguments are separated by commas
+fn parse_entry(line: &str) -> Option<(CheckKey, TryCompileOutput)> {
+    let mut fields = line.split(' ');
+    let command = String::from_utf8(unhex(fields.next()?)?).ok()?;
+    let version_hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let args = match fields.next()? {
+        "" => Vec::new(),
+        args => args
+            .split(',')
+            .map(|arg| String::from_utf8(unhex(arg)?).ok())
+            .collect::<Option<_>>()?,
+    };
+    let code_hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let success = match fields.next()? {
+        "1" => true,
+        "0" => false,
+        _ => return None,
+    };
+    let artifact = unhex(fields.next()?)?;
+
+    let key = CheckKey {
+        command,
+        version_hash,
+        args,
+        code_hash,
+    };
+    Some((key, TryCompileOutput { success, artifact }))
+}
+
+/// 64-bit FNV-1a, which unlike the hasher of `HashMap` is the same in every run
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let mut cache = CheckCache::default();
+        let key = CheckKey::new(
+            "cc",
+            "gcc 12",
+            &["-E".into(), "-DX=a b".into()],
+            "int main() {}",
+        );
+        let output = TryCompileOutput {
+            success: true,
+            artifact: vec![0, 1, 0xff],
+        };
+        cache.insert(key.clone(), output);
+        let empty_args = CheckKey::new("cc", "gcc 12", &[], "");
+        cache.insert(
+            empty_args.clone(),
+            TryCompileOutput {
+                success: false,
+                artifact: vec![],
+            },
+        );
+
+        let saved = cache.save().unwrap();
+        assert_eq!(cache.save(), None);
+
+        let loaded = CheckCache::load(saved.as_bytes());
+        assert_eq!(loaded.get(&key).unwrap().artifact, vec![0, 1, 0xff]);
+        assert!(!loaded.get(&empty_args).unwrap().success);
+        assert!(
+            loaded
+                .get(&CheckKey::new("cc", "gcc 12", &["-E".into()], ""))
+                .is_none()
+        );
+
+        assert!(CheckCache::load(b"garbage\n").entries.is_empty());
+    }
+
+    #[test]
+    fn test_compiler_version() {
+        let mut cache = CheckCache::default();
+        let output = TryCompileOutput {
+            success: true,
+            artifact: vec![],
+        };
+        cache.insert(CheckKey::new("cc", "gcc 12", &[], "int x;"), output);
+
+        assert!(
+            cache
+                .get(&CheckKey::new("cc", "gcc 12", &[], "int x;"))
+                .is_some()
+        );
+        assert!(
+            cache
+                .get(&CheckKey::new("cc", "gcc 13", &[], "int x;"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_save_is_sorted() {
+        let entries = (0..20).map(|i| {
+            let key = CheckKey::new("cc", "gcc 12", &[], &format!("int x{i};"));
+            let output = TryCompileOutput {
+                success: i % 2 == 0,
+                artifact: vec![],
+            };
+            (key, output)
+        });
+
+        let mut forward = CheckCache::default();
+        let mut backward = CheckCache::default();
+        for (key, output) in entries.clone() {
+            forward.insert(key, output);
+        }
+        for (key, output) in entries.rev() {
+            backward.insert(key, output);
+        }
+
+        let saved = forward.save().unwrap();
+        assert_eq!(saved, backward.save().unwrap());
+        let lines = saved.lines().skip(1).collect::<Vec<_>>();
+        assert!(lines.is_sorted());
+    }
+}
@@ -0,0 +1,63 @@
+use core::fmt;
+use core::str::FromStr;
+
+use crate::interpreter::InterpreterError;
+use crate::interpreter::error::bail_runtime_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    C,
+    Cpp,
+    ObjC,
+    ObjCpp,
+    /// Assembly preprocessed and assembled by the C compiler driver
+    Asm,
+    Nasm,
+    Rust,
+}
+
+/// The name of each language as used in `meson.build` files and option
+/// names, and the suffix of the sources written for compiler checks
+const LANGUAGES: &[(Language, &str, &str)] = &[
+    (Language::C, "c", "c"),
+    (Language::Cpp, "cpp", "cpp"),
+    (Language::ObjC, "objc", "m"),
+    (Language::ObjCpp, "objcpp", "mm"),
+    (Language::Asm, "asm", "S"),
+    (Language::Nasm, "nasm", "asm"),
+    (Language::Rust, "rust", "rs"),
+];
+
+impl Language {
+    fn entry(self) -> &'static (Language, &'static str, &'static str) {
+        LANGUAGES
+            .iter()
+            .find(|(lang, ..)| *lang == self)
+            .expect("every language has an entry")
+    }
+
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn suffix(self) -> &'static str {
+        self.entry().2
+    }
+}
+
+impl FromStr for Language {
+    type Err = InterpreterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match LANGUAGES.iter().find(|(_, name, _)| *name == s) {
+            Some((lang, ..)) => Ok(*lang),
+            None => bail_runtime_error!("Tried to use unknown language \"{s}\""),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
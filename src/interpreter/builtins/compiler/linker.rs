@@ -0,0 +1,138 @@
+//! Detection of the linker a compiler driver invokes
+//!
+//! The driver is asked for the linker version with `-Wl,--version`, which
+//! GNU-compatible linkers answer, and then with `-Wl,-v` for Apple's ld64,
+//! which rejects `--version`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::cache::CheckKey;
+use crate::interpreter::Interpreter;
+use crate::path::Path;
+use crate::runtime::TryCompileOutput;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linker {
+    Bfd,
+    Gold,
+    Lld,
+    Mold,
+    Ld64,
+    Solaris,
+    /// The driver did not report a linker we know
+    Unknown,
+}
+
+impl Linker {
+    pub fn detect(command: &Path, flags: &[String], version: &str, interp: &Interpreter) -> Linker {
+        ["-Wl,--version", "-Wl,-v"]
+            .into_iter()
+            .find_map(|arg| parse(&version_output(command, flags, version, arg, interp)))
+            .unwrap_or(Linker::Unknown)
+    }
+
+    /// The id returned by `compiler.get_linker_id()`
+    pub fn id(self) -> &'static str {
+        match self {
+            Linker::Bfd => "ld.bfd",
+            Linker::Gold => "ld.gold",
+            Linker::Lld => "ld.lld",
+            Linker::Mold => "ld.mold",
+            Linker::Ld64 => "ld64",
+            Linker::Solaris => "ld.solaris",
+            Linker::Unknown => "unknown",
+        }
+    }
+
+    /// Arguments that make the linker fail on arguments it only warns
+    /// about, such as unknown `-z` keywords for bfd
+    pub fn fatal_warnings(self) -> &'static [&'static str] {
+        match self {
+            Linker::Bfd | Linker::Gold | Linker::Lld | Linker::Mold => &["-Wl,--fatal-warnings"],
+            Linker::Ld64 => &["-Wl,-fatal_warnings"],
+            Linker::Solaris | Linker::Unknown => &[],
+        }
+    }
+}
+
+/// Runs the driver with a version argument, caching the combined output
+/// alongside the compiler checks
+fn version_output(
+    command: &Path,
+    flags: &[String],
+    version: &str,
+    arg: &str,
+    interp: &Interpreter,
+) -> String {
+    let mut args = flags.to_vec();
+    args.push(arg.into());
+
+    let key = CheckKey::new(command.as_ref(), version, &args, "");
+    if let Some(cached) = interp.check_cache.borrow().get(&key) {
+        return String::from_utf8_lossy(&cached.artifact).into_owned();
+    }
+
+    let argv = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let Ok(output) = interp.os.run_command(command, &argv) else {
+        return String::new();
+    };
+    let text = output.stdout + &output.stderr;
+
+    interp.check_cache.borrow_mut().insert(
+        key,
+        TryCompileOutput {
+            success: output.returncode == 0,
+            artifact: text.clone().into_bytes(),
+        },
+    );
+    text
+}
+
+fn parse(output: &str) -> Option<Linker> {
+    output.lines().find_map(|line| {
+        if line.starts_with("GNU ld") {
+            Some(Linker::Bfd)
+        } else if line.starts_with("GNU gold") {
+            Some(Linker::Gold)
+        } else if line.contains("LLD ") {
+            Some(Linker::Lld)
+        } else if line.starts_with("mold ") {
+            Some(Linker::Mold)
+        } else if line.contains("PROJECT:ld") || line.contains("PROJECT:dyld") {
+            Some(Linker::Ld64)
+        } else if line.contains("Solaris Link Editors") {
+            Some(Linker::Solaris)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let bfd = "collect2 version 12.2.0\n/usr/bin/ld -plugin ... --version\nGNU ld (GNU Binutils for Debian) 2.40\n";
+        assert_eq!(parse(bfd), Some(Linker::Bfd));
+        assert_eq!(
+            parse("GNU gold (GNU Binutils 2.40) 1.16"),
+            Some(Linker::Gold)
+        );
+        assert_eq!(
+            parse("Ubuntu LLD 18.1.3 (compatible with GNU linkers)"),
+            Some(Linker::Lld)
+        );
+        assert_eq!(
+            parse("mold 2.30.0 (compatible with GNU ld)"),
+            Some(Linker::Mold)
+        );
+        assert_eq!(
+            parse("@(#)PROGRAM:ld  PROJECT:ld64-609.8\nBUILD 15:07:46 Dec 18 2020"),
+            Some(Linker::Ld64)
+        );
+        assert_eq!(parse("ld: unknown option: --version"), None);
+    }
+}
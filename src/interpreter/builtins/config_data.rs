@@ -10,11 +10,12 @@ use crate::interpreter::error::ErrorContext as _;
 use crate::interpreter::{
     Interpreter, InterpreterError, MesonObject, Value, bail_runtime_error, bail_type_error,
 };
+use crate::parser::Dict;
 use crate::path::Path;
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct ConfigData {
-    data: HashMap<String, (Value, String)>,
+    data: Dict<(Value, String)>,
 }
 
 impl ConfigData {
@@ -278,3 +279,45 @@ fn configure_with_template(
 
     Ok(template)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_utils::{Harness, MockRuntime};
+
+    #[test]
+    fn test_merge_order() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run(
+            r#"
+conf = configuration_data()
+conf.set('z', 1)
+conf.set('a', 2)
+other = configuration_data()
+other.set('b', 3)
+other.set('z', 4)
+conf.merge_from(other)
+conf.merge_from({'c': 5, 'a': 6})
+"#,
+        )
+        .unwrap();
+
+        // Merged keys keep their position, new ones follow in order
+        let conf = h.get("conf");
+        let conf = conf.as_object::<ConfigData>().unwrap();
+        let entries = conf
+            .data
+            .iter()
+            .map(|(key, (value, _))| (key.as_str(), value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                ("z", Value::Integer(4)),
+                ("a", Value::Integer(6)),
+                ("b", Value::Integer(3)),
+                ("c", Value::Integer(5)),
+            ]
+        );
+    }
+}
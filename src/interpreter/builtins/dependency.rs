@@ -0,0 +1,827 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use super::build_target::{BuildTarget, link_targets};
+use super::builtin_impl;
+use super::disabler::Disabler;
+use super::feature::{Requirement, requirement_or};
+use super::files::files_impl;
+use super::include_directories::include_dirs_impl;
+use super::machine::{build_machine, host_machine};
+use super::utils::flatten;
+use super::version::version_matches;
+use crate::interpreter::error::{ErrorContext as _, bail_runtime_error, bail_type_error};
+use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value, borrow_downcast};
+use crate::machine_file::MachineValue;
+use crate::path::Path;
+use crate::pkg_config::PkgConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    /// Declared by the project with `declare_dependency()`
+    Internal,
+    /// Provided by the system without any lookup, such as `threads`
+    System,
+    /// Found from a `.pc` file
+    PkgConfig,
+    NotFound,
+}
+
+/// What a build target needs to use a library: the arguments to compile and
+/// link with, and the targets of the project to link against
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    pub kind: DependencyKind,
+    pub version: Option<String>,
+    pub compile_args: Vec<String>,
+    pub link_args: Vec<String>,
+    pub include_dirs: Vec<Path>,
+    pub link_with: Vec<BuildTarget>,
+    pub link_whole: Vec<BuildTarget>,
+    pub sources: Vec<Path>,
+    pub variables: HashMap<String, String>,
+}
+
+impl MesonObject for Dependency {
+    builtin_impl!(
+        found,
+        version,
+        name,
+        type_name,
+        get_variable,
+        partial_dependency
+    );
+}
+
+impl Dependency {
+    pub fn not_found(name: &str) -> Self {
+        Dependency {
+            name: name.into(),
+            kind: DependencyKind::NotFound,
+            version: None,
+            compile_args: Vec::new(),
+            link_args: Vec::new(),
+            include_dirs: Vec::new(),
+            link_with: Vec::new(),
+            link_whole: Vec::new(),
+            sources: Vec::new(),
+            variables: HashMap::new(),
+        }
+    }
+
+    pub fn is_found(&self) -> bool {
+        self.kind != DependencyKind::NotFound
+    }
+
+    /// Adds the arguments, targets and sources of another dependency
+    fn merge(&mut self, other: &Dependency) {
+        self.compile_args.extend(other.compile_args.iter().cloned());
+        self.link_args.extend(other.link_args.iter().cloned());
+        self.include_dirs.extend(other.include_dirs.iter().cloned());
+        self.link_with.extend(other.link_with.iter().cloned());
+        self.link_whole.extend(other.link_whole.iter().cloned());
+        self.sources.extend(other.sources.iter().cloned());
+    }
+
+    fn found(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(self.is_found()))
+    }
+
+    fn version(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let version = self.version.as_deref().unwrap_or("unknown");
+        Ok(Value::String(version.into()))
+    }
+
+    fn name(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let name = match self.kind {
+            DependencyKind::Internal => "internal",
+            _ => &self.name,
+        };
+        Ok(Value::String(name.into()))
+    }
+
+    fn type_name(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let type_name = match self.kind {
+            DependencyKind::Internal => "internal",
+            DependencyKind::System => "system",
+            DependencyKind::PkgConfig => "pkgconfig",
+            DependencyKind::NotFound => "not-found",
+        };
+        Ok(Value::String(type_name.into()))
+    }
+
+    /// Looks up a variable by the positional name or the name for the kind
+    /// of dependency, falling back to `default_value:`
+    fn get_variable(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let kind_key = match self.kind {
+            DependencyKind::Internal => Some("internal"),
+            DependencyKind::PkgConfig => Some("pkgconfig"),
+            DependencyKind::System | DependencyKind::NotFound => None,
+        };
+
+        let names = args
+            .first()
+            .into_iter()
+            .chain(kind_key.and_then(|key| kwargs.get(key)))
+            .map(|v| {
+                v.as_string()
+                    .context_type("Expected the variable name to be a string")
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(value) = names.iter().find_map(|name| self.variables.get(*name)) {
+            return Ok(Value::String(value.clone()));
+        }
+
+        match kwargs.get("default_value") {
+            Some(default) => Ok(default.clone()),
+            None => bail_runtime_error!(
+                "Could not get variable {} of dependency {} and no default value was given",
+                names.first().copied().unwrap_or_default(),
+                self.name
+            ),
+        }
+    }
+
+    /// Returns a copy of the dependency with only the parts that are asked
+    /// for
+    fn partial_dependency(
+        &self,
+        _args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let part = |key: &str| {
+            kwargs
+                .get(key)
+                .map(Value::as_boolean)
+                .transpose()
+                .with_context_type(|| format!("Expected '{key}' keyword argument to be a boolean"))
+                .map(Option::unwrap_or_default)
+        };
+
+        let mut partial = Dependency {
+            name: self.name.clone(),
+            kind: self.kind,
+            version: self.version.clone(),
+            variables: self.variables.clone(),
+            ..Dependency::not_found(&self.name)
+        };
+        if part("compile_args")? {
+            partial.compile_args = self.compile_args.clone();
+        }
+        if part("includes")? {
+            partial.include_dirs = self.include_dirs.clone();
+        }
+        if part("link_args")? {
+            partial.link_args = self.link_args.clone();
+        }
+        if part("links")? {
+            partial.link_with = self.link_with.clone();
+            partial.link_whole = self.link_whole.clone();
+        }
+        if part("sources")? {
+            partial.sources = self.sources.clone();
+        }
+        Ok(partial.into_object())
+    }
+}
+
+/// Reads a `dependencies:` keyword argument, leaving out the dependencies
+/// that were not found
+pub(super) fn dependencies_impl(
+    kwargs: &HashMap<String, Value>,
+) -> Result<Vec<Dependency>, InterpreterError> {
+    let mut deps = Vec::new();
+    for dep in flatten(&kwargs.get("dependencies")) {
+        let Value::Object(obj) = dep else {
+            bail_type_error!("Expected elements of 'dependencies' to be dependencies");
+        };
+        let Some(dep) = borrow_downcast::<Dependency>(obj) else {
+            bail_type_error!("Expected elements of 'dependencies' to be dependencies");
+        };
+        if dep.is_found() {
+            deps.push(dep.clone());
+        }
+    }
+    Ok(deps)
+}
+
+pub fn dependency(
+    args: Vec<Value>,
+    kwargs: HashMap<String, Value>,
+    interp: &mut Interpreter,
+) -> Result<Value, InterpreterError> {
+    let names = flatten(&args)
+        .map(|v| {
+            v.as_string()
+                .context_type("Expected dependency names to be strings")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let display_name = names.first().copied().unwrap_or_default();
+
+    let requirement = requirement_or(&kwargs, Requirement::Required)?;
+
+    let disabler = kwargs
+        .get("disabler")
+        .map(Value::as_boolean)
+        .transpose()
+        .context_type("Expected 'disabler' keyword argument to be a boolean")?
+        .unwrap_or(false);
+
+    let version_kwarg = kwargs.get("version");
+    let versions = flatten(&version_kwarg)
+        .map(|v| {
+            v.as_string()
+                .context_type("Expected 'version' keyword argument to be strings")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // An empty name never matches anything, which is how projects spell a
+    // dependency that is not found
+    let lookups = match requirement {
+        Requirement::Disabled => Vec::new(),
+        _ => names
+            .iter()
+            .copied()
+            .filter(|name| !name.is_empty())
+            .collect(),
+    };
+
+    let mut mismatch = None;
+    for name in lookups {
+        let Some(dep) = lookup(name, &kwargs, interp)? else {
+            continue;
+        };
+
+        let version = dep.version.as_deref();
+        let mut matches = true;
+        for req in &versions {
+            matches &= version.map(|v| version_matches(v, req)).transpose()? == Some(true);
+        }
+        if matches {
+            return Ok(dep.into_object());
+        }
+        mismatch = Some(String::from(version.unwrap_or("unknown")));
+    }
+
+    if requirement == Requirement::Required {
+        match mismatch {
+            Some(found) => bail_runtime_error!(
+                "Dependency \"{display_name}\" not found, found {found} but need: {}",
+                versions.join(", ")
+            ),
+            None => bail_runtime_error!("Dependency \"{display_name}\" not found"),
+        }
+    }
+
+    if let Some(message) = kwargs.get("not_found_message") {
+        let message = message
+            .as_string()
+            .context_type("Expected 'not_found_message' keyword argument to be a string")?;
+        interp.os.print(message);
+    }
+
+    if disabler {
+        return Ok(Disabler.into_object());
+    }
+    Ok(Dependency::not_found(display_name).into_object())
+}
+
+/// Finds a dependency by name with the methods allowed by `method:`
+fn lookup(
+    name: &str,
+    kwargs: &HashMap<String, Value>,
+    interp: &Interpreter,
+) -> Result<Option<Dependency>, InterpreterError> {
+    if name == "threads" {
+        let system = host_machine(interp)?.system;
+        let args = match system.as_str() {
+            "windows" => Vec::new(),
+            _ => Vec::from(["-pthread".into()]),
+        };
+        return Ok(Some(Dependency {
+            kind: DependencyKind::System,
+            compile_args: args.clone(),
+            link_args: args,
+            ..Dependency::not_found(name)
+        }));
+    }
+
+    let method = kwargs
+        .get("method")
+        .map(Value::as_string)
+        .transpose()
+        .context_type("Expected 'method' keyword argument to be a string")?
+        .unwrap_or("auto");
+
+    match method {
+        "auto" | "pkg-config" => pkg_config_lookup(name, kwargs, interp),
+        "builtin" | "system" | "config-tool" | "cmake" | "extraframework" | "dub" | "sysconfig"
+        | "qmake" => Ok(None),
+        _ => bail_runtime_error!("Unknown dependency method '{method}'"),
+    }
+}
+
+fn pkg_config_lookup(
+    name: &str,
+    kwargs: &HashMap<String, Value>,
+    interp: &Interpreter,
+) -> Result<Option<Dependency>, InterpreterError> {
+    let flag = |key: &str| {
+        kwargs
+            .get(key)
+            .map(Value::as_boolean)
+            .transpose()
+            .with_context_type(|| format!("Expected '{key}' keyword argument to be a boolean"))
+            .map(Option::unwrap_or_default)
+    };
+    let native = flag("native")?;
+    let static_libs = flag("static")?;
+
+    let sysroot = interp
+        .machine_files
+        .get(native)
+        .and_then(|file| file.get("properties", "sys_root"))
+        .map(MachineValue::coerce_string);
+
+    let read_file = |path: &Path| interp.os.read_file(path).ok();
+    let pkg_config = PkgConfig {
+        dirs: pkg_config_dirs(interp, native)?,
+        sysroot,
+        read_file: &read_file,
+    };
+
+    // A package that can't be resolved is not found, leaving it to
+    // `required:` whether that fails the build
+    let package = match pkg_config.resolve(name, static_libs) {
+        Ok(package) => package,
+        Err(err) => {
+            interp.os.print(&format!(
+                "Failed to resolve pkg-config package {name}: {err:#}"
+            ));
+            return Ok(None);
+        }
+    };
+
+    Ok(package.map(|package| Dependency {
+        kind: DependencyKind::PkgConfig,
+        version: Some(package.version),
+        compile_args: package.cflags,
+        link_args: package.libs,
+        variables: package.variables,
+        ..Dependency::not_found(name)
+    }))
+}
+
+/// The directories searched for `.pc` files: `pkg_config_path`, then the
+/// `pkg_config_libdir` property of the machine file or, for the build
+/// machine, `PKG_CONFIG_PATH` and the usual system directories
+fn pkg_config_dirs(interp: &Interpreter, native: bool) -> Result<Vec<Path>, InterpreterError> {
+    let mut dirs = Vec::new();
+
+    let option = interp.get_option("pkg_config_path");
+    for dir in flatten(&option.as_ref()) {
+        let dir = dir
+            .as_string()
+            .context_type("Expected 'pkg_config_path' option to be an array of strings")?;
+        dirs.push(Path::from(dir));
+    }
+
+    let libdir = interp
+        .machine_files
+        .get(native)
+        .and_then(|file| file.get("properties", "pkg_config_libdir"));
+    if let Some(libdir) = libdir {
+        match libdir {
+            MachineValue::Array(items) => {
+                dirs.extend(items.iter().map(|dir| Path::from(dir.coerce_string())));
+            }
+            value => dirs.extend(value.coerce_string().split(':').map(Path::from)),
+        }
+        return Ok(dirs);
+    }
+
+    // The system directories only have packages for the build machine
+    if !native && interp.machine_files.cross.is_some() {
+        return Ok(dirs);
+    }
+
+    if let Some(path) = interp.os.get_env("PKG_CONFIG_PATH") {
+        let path = path.split(':').filter(|dir| !dir.is_empty());
+        dirs.extend(path.map(Path::from));
+    }
+
+    let build = build_machine(interp)?;
+    dirs.extend(["/usr/local/lib/pkgconfig", "/usr/local/share/pkgconfig"].map(Path::from));
+    if build.system == "linux" {
+        let multiarch = format!("/usr/lib/{}/pkgconfig", build.multiarch());
+        dirs.push(Path::from(multiarch));
+    }
+    dirs.extend(["/usr/lib/pkgconfig", "/usr/share/pkgconfig"].map(Path::from));
+    Ok(dirs)
+}
+
+pub fn declare_dependency(
+    _args: Vec<Value>,
+    kwargs: HashMap<String, Value>,
+    interp: &mut Interpreter,
+) -> Result<Value, InterpreterError> {
+    let strings = |key: &str| {
+        flatten(&kwargs.get(key))
+            .map(|v| v.as_string().map(String::from))
+            .collect::<Result<Vec<_>, _>>()
+            .with_context_type(|| format!("Expected elements of '{key}' to be strings"))
+    };
+
+    let version = match kwargs.get("version") {
+        Some(version) => version
+            .as_string()
+            .context_type("Expected 'version' keyword argument to be a string")?
+            .into(),
+        None => interp.meson.borrow().project_version.clone(),
+    };
+
+    let mut dep = Dependency {
+        name: String::new(),
+        kind: DependencyKind::Internal,
+        version: Some(version),
+        compile_args: strings("compile_args")?,
+        link_args: strings("link_args")?,
+        include_dirs: include_dirs_impl(kwargs.get("include_directories"), interp)?,
+        link_with: link_targets(&kwargs, "link_with", None)?,
+        link_whole: link_targets(&kwargs, "link_whole", None)?,
+        sources: files_impl(&kwargs.get("sources"), interp)?
+            .into_iter()
+            .map(|f| f.path)
+            .collect(),
+        variables: variables_impl(&kwargs)?.into_iter().collect(),
+    };
+    for other in dependencies_impl(&kwargs)? {
+        dep.merge(&other);
+    }
+
+    Ok(dep.into_object())
+}
+
+/// Parses the `variables` keyword argument, given either as a dictionary or
+/// as a list of `name=value` strings, keeping the variables in order
+pub(super) fn variables_impl(
+    kwargs: &HashMap<String, Value>,
+) -> Result<Vec<(String, String)>, InterpreterError> {
+    match kwargs.get("variables") {
+        None => Ok(Vec::new()),
+        Some(Value::Dict(dict)) => dict
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), v.as_string()?.into())))
+            .collect::<Result<_, InterpreterError>>()
+            .context_type("Expected the values of 'variables' to be strings"),
+        Some(variables) => flatten(&Some(variables))
+            .map(|var| {
+                let var = var
+                    .as_string()
+                    .context_type("Expected elements of 'variables' to be strings")?;
+                match var.split_once('=') {
+                    Some((k, v)) => Ok((k.trim().into(), v.trim().into())),
+                    None => {
+                        bail_runtime_error!("Variable '{var}' must have a value separated by '='")
+                    }
+                }
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_utils::{Harness, MockRuntime};
+
+    fn harness() -> Harness {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run("project('t')").unwrap();
+        h
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    fn error(h: &mut Harness, code: &str) -> String {
+        h.run(code).unwrap_err().root().to_string()
+    }
+
+    #[test]
+    fn test_declare_dependency() {
+        let mut h = harness();
+        h.run(
+            r#"
+dep = declare_dependency(
+  compile_args: '-DFOO',
+  include_directories: 'inc',
+  link_args: '-lbar',
+  version: '1.2',
+  variables: {'datadir': '/data'},
+)
+found = dep.found()
+version = dep.version()
+name = dep.name()
+positional = dep.get_variable('datadir')
+internal = dep.get_variable(internal: 'datadir')
+pkgconfig = dep.get_variable(pkgconfig: 'datadir', default_value: 'none')
+fallback = dep.get_variable('missing', default_value: 'default')
+exe = executable('exe', dependencies: dep)
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("found"), Value::Boolean(true));
+        assert_eq!(h.get("version"), string("1.2"));
+        assert_eq!(h.get("name"), string("internal"));
+        assert_eq!(h.get("positional"), string("/data"));
+        assert_eq!(h.get("internal"), string("/data"));
+        // Only the key for the kind of dependency is looked at
+        assert_eq!(h.get("pkgconfig"), string("none"));
+        assert_eq!(h.get("fallback"), string("default"));
+
+        let exe = h.get("exe");
+        let exe = exe.as_object::<BuildTarget>().unwrap();
+        assert!(exe.flags.contains(&"-DFOO".into()));
+        assert!(exe.include_dirs.contains(&Path::from("/src/inc")));
+        assert_eq!(exe.link_args, ["-lbar"]);
+
+        let err = error(&mut h, "dep.get_variable('missing')");
+        assert!(err.contains("Could not get variable missing"), "{err}");
+    }
+
+    #[test]
+    fn test_partial_dependency() {
+        let mut h = harness();
+        h.run(
+            r#"
+dep = declare_dependency(compile_args: '-DFOO', include_directories: 'inc', link_args: '-lbar')
+partial = dep.partial_dependency(compile_args: true, link_args: true)
+exe = executable('exe', dependencies: partial)
+"#,
+        )
+        .unwrap();
+
+        let exe = h.get("exe");
+        let exe = exe.as_object::<BuildTarget>().unwrap();
+        assert!(exe.flags.contains(&"-DFOO".into()));
+        assert!(!exe.include_dirs.contains(&Path::from("/src/inc")));
+        assert_eq!(exe.link_args, ["-lbar"]);
+    }
+
+    #[test]
+    fn test_dependency() {
+        let mut h = harness();
+        h.run(
+            r#"
+threads = dependency('threads')
+threads_name = threads.name()
+threads_version = threads.version()
+missing = dependency('missing', required: false, not_found_message: 'no missing')
+missing_found = missing.found()
+empty = dependency('', required: false)
+disabled = dependency('missing', required: false, disabler: true)
+too_old = dependency('threads', version: '>=1', required: false)
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("threads_name"), string("threads"));
+        assert_eq!(h.get("threads_version"), string("unknown"));
+        assert_eq!(h.get("missing_found"), Value::Boolean(false));
+        assert_eq!(*h.os.printed.borrow(), ["no missing"]);
+        let empty = h.get("empty");
+        assert!(!empty.as_object::<Dependency>().unwrap().is_found());
+        assert!(h.get("disabled").is_disabler());
+        let too_old = h.get("too_old");
+        assert!(!too_old.as_object::<Dependency>().unwrap().is_found());
+
+        let err = error(&mut h, "dependency('missing')");
+        assert!(err.contains("Dependency \"missing\" not found"), "{err}");
+        let err = error(&mut h, "dependency('threads', version: '>=1')");
+        assert!(err.contains("found unknown but need: >=1"), "{err}");
+    }
+
+    /// A runtime with the given `.pc` files
+    fn pkg_config_runtime(files: &[(&str, &str)]) -> MockRuntime {
+        let os = MockRuntime::default();
+        for (path, content) in files {
+            os.files
+                .borrow_mut()
+                .insert(path.to_string(), content.as_bytes().into());
+        }
+        os
+    }
+
+    /// A harness whose runtime has the given `.pc` files
+    fn pkg_config_harness(files: &[(&str, &str)]) -> Harness {
+        let mut h = Harness::new(pkg_config_runtime(files));
+        h.run("project('t')").unwrap();
+        h
+    }
+
+    fn version(h: &mut Harness, code: &str) -> Value {
+        h.run(&format!("v = {code}.version()")).unwrap();
+        h.get("v")
+    }
+
+    #[test]
+    fn test_pkg_config_dependency() {
+        let mut h = pkg_config_harness(&[(
+            "/usr/lib/pkgconfig/zlib.pc",
+            "prefix=/opt/zlib\nlibdir=${prefix}/lib\nincludedir=${prefix}/include\n\n\
+             Name: zlib\nDescription: zlib compression library\nVersion: 1.2.13\n\
+             Cflags: -I${includedir} -DZLIB\nLibs: -L${libdir} -lz\n",
+        )]);
+        h.run(
+            r#"
+dep = dependency('zlib', method: 'pkg-config', version: '>=1.2')
+found = dep.found()
+type = dep.type_name()
+version = dep.version()
+prefix = dep.get_variable(pkgconfig: 'prefix')
+libdir = dep.get_variable('libdir')
+exe = executable('exe', dependencies: dep)
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("found"), Value::Boolean(true));
+        assert_eq!(h.get("type"), string("pkgconfig"));
+        assert_eq!(h.get("version"), string("1.2.13"));
+        assert_eq!(h.get("prefix"), string("/opt/zlib"));
+        assert_eq!(h.get("libdir"), string("/opt/zlib/lib"));
+
+        let exe = h.get("exe");
+        let exe = exe.as_object::<BuildTarget>().unwrap();
+        assert!(exe.flags.contains(&"-I/opt/zlib/include".into()));
+        assert!(exe.flags.contains(&"-DZLIB".into()));
+        assert_eq!(exe.link_args, ["-L/opt/zlib/lib", "-lz"]);
+
+        let err = error(&mut h, "dependency('zlib', version: '>=2')");
+        assert!(
+            err.contains("Dependency \"zlib\" not found, found 1.2.13 but need: >=2"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_pkg_config_path() {
+        let files = [
+            (
+                "/usr/lib/pkgconfig/foo.pc",
+                "Name: foo\nDescription: Foo\nVersion: 1.0\n",
+            ),
+            ("/a/foo.pc", "Name: foo\nDescription: Foo\nVersion: 2.0\n"),
+            ("/b/foo.pc", "Name: foo\nDescription: Foo\nVersion: 3.0\n"),
+        ];
+
+        let mut h = pkg_config_harness(&files);
+        assert_eq!(version(&mut h, "dependency('foo')"), string("1.0"));
+
+        // The option is searched before the system directories, in order
+        h.interp.set_option("pkg_config_path", "/a").unwrap();
+        assert_eq!(version(&mut h, "dependency('foo')"), string("2.0"));
+        h.interp.set_option("pkg_config_path", "/b,/a").unwrap();
+        assert_eq!(version(&mut h, "dependency('foo')"), string("3.0"));
+        h.interp
+            .set_option("pkg_config_path", "['/a', '/b']")
+            .unwrap();
+        assert_eq!(version(&mut h, "dependency('foo')"), string("2.0"));
+        h.run("path = get_option('pkg_config_path')").unwrap();
+        assert_eq!(
+            h.get("path"),
+            Value::Array(vec![string("/a"), string("/b")])
+        );
+
+        // A machine file may give it as a string or an array
+        for (native, expected) in [
+            ("[built-in options]\npkg_config_path = '/b'\n", "3.0"),
+            (
+                "[built-in options]\npkg_config_path = ['/a', '/b']\n",
+                "2.0",
+            ),
+        ] {
+            let os = pkg_config_runtime(&files);
+            let mut h = Harness::with_machine_files(os, None, Some(native));
+            h.run("project('t')").unwrap();
+            assert_eq!(version(&mut h, "dependency('foo')"), string(expected));
+        }
+    }
+
+    #[test]
+    fn test_pkg_config_machine_properties() {
+        let files = [
+            (
+                "/usr/lib/pkgconfig/foo.pc",
+                "Name: foo\nDescription: Foo\nVersion: 1.0\n",
+            ),
+            (
+                "/sysroot/lib/pkgconfig/foo.pc",
+                "prefix=/usr\n\nName: foo\nDescription: Foo\nVersion: 2.0\n\
+                 Cflags: -I${prefix}/include/foo\nLibs: -L${prefix}/lib/foo -lfoo\n",
+            ),
+            (
+                "/sysroot/lib/pkgconfig/bar.pc",
+                "Name: bar\nDescription: Bar\nVersion: 1.0\n\
+                 Cflags: -I${pc_sysrootdir}/opt/include\n",
+            ),
+        ];
+
+        // Without pkg_config_libdir, a cross build finds no host packages
+        let cross = "[properties]\nsys_root = '/sysroot'\n";
+        let mut h = Harness::with_machine_files(pkg_config_runtime(&files), Some(cross), None);
+        h.run("project('t')\nfound = dependency('foo', required: false).found()")
+            .unwrap();
+        assert_eq!(h.get("found"), Value::Boolean(false));
+        assert_eq!(
+            version(&mut h, "dependency('foo', native: true)"),
+            string("1.0")
+        );
+
+        // It replaces the system directories, and the sysroot prefixes the
+        // directories of the flags
+        let cross = "[properties]\nsys_root = '/sysroot'\n\
+                     pkg_config_libdir = '/sysroot/lib/pkgconfig'\n";
+        let mut h = Harness::with_machine_files(pkg_config_runtime(&files), Some(cross), None);
+        h.run(
+            r#"
+project('t')
+foo = dependency('foo')
+version = foo.version()
+sysroot = dependency('bar').get_variable(pkgconfig: 'pc_sysrootdir')
+exe = executable('exe', dependencies: foo)
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("version"), string("2.0"));
+        assert_eq!(h.get("sysroot"), string("/sysroot"));
+        let exe = h.get("exe");
+        let exe = exe.as_object::<BuildTarget>().unwrap();
+        assert!(exe.flags.contains(&"-I/sysroot/usr/include/foo".into()));
+        assert_eq!(exe.link_args, ["-L/sysroot/usr/lib/foo", "-lfoo"]);
+    }
+
+    #[test]
+    fn test_unresolvable_package() {
+        let mut h = pkg_config_harness(&[
+            (
+                "/usr/lib/pkgconfig/foo.pc",
+                "Name: foo\nDescription: Foo\nVersion: 1.0\nRequires: missing\n",
+            ),
+            (
+                "/usr/lib/pkgconfig/bad.pc",
+                "Name: bad\nDescription: Bad\nVersion: 1.0\nLibs: ${undefined}\n",
+            ),
+        ]);
+        h.run(
+            r#"
+foo = dependency('foo', required: false, not_found_message: 'no foo')
+foo_found = foo.found()
+bad = dependency('bad', required: false, disabler: true)
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("foo_found"), Value::Boolean(false));
+        assert!(h.get("bad").is_disabler());
+
+        let printed = h.os.printed.borrow().clone();
+        assert_eq!(printed.len(), 3, "{printed:?}");
+        assert!(printed[0].contains("Package 'missing', required by 'foo', not found"));
+        assert_eq!(printed[1], "no foo");
+        assert!(printed[2].contains("Variable 'undefined' not defined"));
+
+        let err = error(&mut h, "dependency('foo')");
+        assert!(err.contains("Dependency \"foo\" not found"), "{err}");
+    }
+}
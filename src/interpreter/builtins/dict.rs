@@ -5,9 +5,10 @@ use hashbrown::HashMap;
 
 use crate::interpreter::error::ErrorContext;
 use crate::interpreter::{Interpreter, InterpreterError, Value};
+use crate::parser::Dict;
 
 pub fn get(
-    obj: &HashMap<String, Value>,
+    obj: &Dict<Value>,
     args: Vec<Value>,
     _kwargs: HashMap<String, Value>,
     _interp: &mut Interpreter,
@@ -27,7 +28,7 @@ pub fn get(
 }
 
 pub fn has_key(
-    obj: &HashMap<String, Value>,
+    obj: &Dict<Value>,
     args: Vec<Value>,
     _kwargs: HashMap<String, Value>,
     _interp: &mut Interpreter,
@@ -42,7 +43,7 @@ pub fn has_key(
 }
 
 pub fn keys(
-    obj: &HashMap<String, Value>,
+    obj: &Dict<Value>,
     _args: Vec<Value>,
     _kwargs: HashMap<String, Value>,
     _interp: &mut Interpreter,
@@ -52,7 +53,7 @@ pub fn keys(
 }
 
 pub fn values(
-    obj: &HashMap<String, Value>,
+    obj: &Dict<Value>,
     _args: Vec<Value>,
     _kwargs: HashMap<String, Value>,
     _interp: &mut Interpreter,
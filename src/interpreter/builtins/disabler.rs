@@ -0,0 +1,61 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use super::builtin_impl;
+use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value, borrow_downcast};
+
+/// A value that disables everything it touches: calls taking it as an
+/// argument and methods called on it return a disabler, and an `if` on a
+/// disabler runs none of its branches
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disabler;
+
+impl MesonObject for Disabler {
+    builtin_impl!(found);
+}
+
+impl Disabler {
+    fn found(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(false))
+    }
+}
+
+impl Value {
+    pub fn is_disabler(&self) -> bool {
+        matches!(self, Value::Object(obj) if borrow_downcast::<Disabler>(obj).is_some())
+    }
+}
+
+/// Whether any argument is a disabler, or an array containing one
+pub fn is_disabled(args: &[Value], kwargs: &HashMap<String, Value>) -> bool {
+    fn disabled(value: &Value) -> bool {
+        match value {
+            Value::Array(items) => items.iter().any(disabled),
+            value => value.is_disabler(),
+        }
+    }
+    args.iter().any(disabled) || kwargs.values().any(disabled)
+}
+
+pub fn disabler(
+    _args: Vec<Value>,
+    _kwargs: HashMap<String, Value>,
+    _interp: &mut Interpreter,
+) -> Result<Value, InterpreterError> {
+    Ok(Disabler.into_object())
+}
+
+pub fn is_disabler(
+    args: Vec<Value>,
+    _kwargs: HashMap<String, Value>,
+    _interp: &mut Interpreter,
+) -> Result<Value, InterpreterError> {
+    Ok(Value::Boolean(args.first().is_some_and(Value::is_disabler)))
+}
@@ -5,6 +5,7 @@ use alloc::vec::Vec;
 use hashbrown::HashMap;
 
 use super::builtin_impl;
+use super::feature::{Requirement, requirement};
 use crate::interpreter::error::ErrorContext;
 use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
 use crate::path::Path;
@@ -54,8 +55,26 @@ pub fn find_program(
 
     let prog = Path::from(prog);
 
+    let required = match requirement(&kwargs)? {
+        Requirement::Disabled => return Ok(ExternalProgram { full_path: None }.into_object()),
+        requirement => requirement == Requirement::Required,
+    };
+
+    let native = kwargs
+        .get("native")
+        .map(Value::as_boolean)
+        .transpose()
+        .context_type("The 'native' keyword argument must be a boolean")?
+        .unwrap_or(false);
+
+    // A program named in the machine file's [binaries] replaces the lookup
+    let lookup = match interp.machine_files.binary(prog.as_ref(), native) {
+        Some((bin, _)) => Path::from(bin),
+        None => prog.clone(),
+    };
+
     // Simple check if program exists in PATH
-    let full_path = interp.os.find_program(&prog, &interp.current_dir).ok();
+    let full_path = interp.os.find_program(&lookup, &interp.current_dir).ok();
 
     let found = full_path.is_some();
 
@@ -65,12 +84,6 @@ pub fn find_program(
         return Ok(program);
     }
 
-    let required = kwargs
-        .get("required")
-        .map(Value::as_boolean)
-        .transpose()?
-        .unwrap_or(false);
-
     if required {
         return Err(InterpreterError::RuntimeError(format!(
             "Program '{prog}' not found"
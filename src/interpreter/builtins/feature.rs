@@ -0,0 +1,397 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use hashbrown::HashMap;
+
+use super::builtin_impl;
+use crate::interpreter::error::{ErrorContext as _, bail_runtime_error, bail_type_error};
+use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value, borrow_downcast};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureState {
+    Enabled,
+    Disabled,
+    Auto,
+}
+
+impl FromStr for FeatureState {
+    type Err = InterpreterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "enabled" => Ok(FeatureState::Enabled),
+            "disabled" => Ok(FeatureState::Disabled),
+            "auto" => Ok(FeatureState::Auto),
+            _ => bail_runtime_error!(
+                "Invalid feature value '{s}', allowed values are: enabled, disabled, auto"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for FeatureState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FeatureState::Enabled => "enabled",
+            FeatureState::Disabled => "disabled",
+            FeatureState::Auto => "auto",
+        })
+    }
+}
+
+/// The value of a `feature` option, as returned by `get_option()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    pub name: String,
+    pub state: FeatureState,
+}
+
+impl MesonObject for Feature {
+    builtin_impl!(
+        enabled,
+        disabled,
+        auto,
+        allowed,
+        require,
+        disable_auto_if,
+        enable_if,
+        disable_if
+    );
+}
+
+impl Feature {
+    pub fn from_value(value: &Value) -> Option<Feature> {
+        match value {
+            Value::Object(obj) => borrow_downcast::<Feature>(obj).map(|f| f.clone()),
+            _ => None,
+        }
+    }
+
+    fn with_state(&self, state: FeatureState) -> Value {
+        Feature {
+            name: self.name.clone(),
+            state,
+        }
+        .into_object()
+    }
+
+    fn enabled(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(self.state == FeatureState::Enabled))
+    }
+
+    fn disabled(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(self.state == FeatureState::Disabled))
+    }
+
+    fn auto(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(self.state == FeatureState::Auto))
+    }
+
+    fn allowed(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(self.state != FeatureState::Disabled))
+    }
+
+    /// Disables an auto feature if the condition does not hold, or fails if
+    /// the feature is enabled
+    fn require(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        if condition(&args)? {
+            return Ok(self.with_state(self.state));
+        }
+        if self.state == FeatureState::Enabled {
+            self.fail("cannot be enabled", &kwargs)?;
+        }
+        Ok(self.with_state(FeatureState::Disabled))
+    }
+
+    fn disable_auto_if(
+        &self,
+        args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        if condition(&args)? && self.state == FeatureState::Auto {
+            return Ok(self.with_state(FeatureState::Disabled));
+        }
+        Ok(self.with_state(self.state))
+    }
+
+    fn enable_if(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        if !condition(&args)? {
+            return Ok(self.with_state(self.state));
+        }
+        if self.state == FeatureState::Disabled {
+            self.fail("cannot be enabled", &kwargs)?;
+        }
+        Ok(self.with_state(FeatureState::Enabled))
+    }
+
+    fn disable_if(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        if !condition(&args)? {
+            return Ok(self.with_state(self.state));
+        }
+        if self.state == FeatureState::Enabled {
+            self.fail("cannot be disabled", &kwargs)?;
+        }
+        Ok(self.with_state(FeatureState::Disabled))
+    }
+
+    fn fail(&self, reason: &str, kwargs: &HashMap<String, Value>) -> Result<(), InterpreterError> {
+        let message = kwargs
+            .get("error_message")
+            .map(Value::as_string)
+            .transpose()
+            .context_type("Expected 'error_message' keyword argument to be a string")?
+            .unwrap_or_default();
+
+        if message.is_empty() {
+            bail_runtime_error!("Feature {} {reason}", self.name);
+        }
+        bail_runtime_error!("Feature {} {reason}: {message}", self.name);
+    }
+}
+
+fn condition(args: &[Value]) -> Result<bool, InterpreterError> {
+    match args.first() {
+        Some(Value::Boolean(b)) => Ok(*b),
+        _ => bail_type_error!("Expected a boolean condition as the first argument"),
+    }
+}
+
+/// How a lookup was asked for through its `required:` keyword argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    Required,
+    Optional,
+    /// A disabled feature was passed: the lookup is skipped and reports not found
+    Disabled,
+}
+
+/// Reads a `required:` keyword argument, which is either a boolean or a
+/// feature. Auto features make the lookup optional.
+pub fn requirement(kwargs: &HashMap<String, Value>) -> Result<Requirement, InterpreterError> {
+    requirement_or(kwargs, Requirement::Optional)
+}
+
+/// Reads a `required:` keyword argument that defaults to `default`
+pub fn requirement_or(
+    kwargs: &HashMap<String, Value>,
+    default: Requirement,
+) -> Result<Requirement, InterpreterError> {
+    let Some(required) = kwargs.get("required") else {
+        return Ok(default);
+    };
+
+    if let Value::Boolean(required) = required {
+        return Ok(match required {
+            true => Requirement::Required,
+            false => Requirement::Optional,
+        });
+    }
+
+    match Feature::from_value(required).map(|f| f.state) {
+        Some(FeatureState::Enabled) => Ok(Requirement::Required),
+        Some(FeatureState::Auto) => Ok(Requirement::Optional),
+        Some(FeatureState::Disabled) => Ok(Requirement::Disabled),
+        None => bail_type_error!("The 'required' keyword argument must be a boolean or a feature"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString as _;
+
+    use crate::interpreter::test_utils::{Harness, MockRuntime};
+    use crate::interpreter::{InterpreterError, Value};
+
+    const OPTIONS: &str = r#"
+option('on', type: 'feature', value: 'enabled')
+option('off', type: 'feature', value: 'disabled')
+option('maybe', type: 'feature', value: 'auto')
+on = get_option('on')
+off = get_option('off')
+maybe = get_option('maybe')
+"#;
+
+    fn harness() -> Harness {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run(OPTIONS).unwrap();
+        h
+    }
+
+    fn error(h: &mut Harness, code: &str) -> String {
+        let err: InterpreterError = h.run(code).unwrap_err();
+        err.root().to_string()
+    }
+
+    #[test]
+    fn test_state_methods() {
+        let mut h = harness();
+        h.run(
+            r#"
+states = []
+foreach f : [on, off, maybe]
+  states += [[f.enabled(), f.disabled(), f.auto(), f.allowed()]]
+endforeach
+"#,
+        )
+        .unwrap();
+
+        let row = |values: [bool; 4]| Value::Array(values.map(Value::Boolean).to_vec());
+        assert_eq!(
+            h.get("states"),
+            Value::Array(alloc::vec![
+                row([true, false, false, true]),
+                row([false, true, false, false]),
+                row([false, false, true, true]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_require() {
+        let mut h = harness();
+        h.run(
+            r#"
+kept = maybe.require(true).auto()
+disabled = maybe.require(false).disabled()
+enabled = on.require(true).enabled()
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("kept"), Value::Boolean(true));
+        assert_eq!(h.get("disabled"), Value::Boolean(true));
+        assert_eq!(h.get("enabled"), Value::Boolean(true));
+
+        let err = error(&mut h, "on.require(false, error_message: 'needs x')");
+        assert!(
+            err.contains("Feature on cannot be enabled: needs x"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_disable_auto_if() {
+        let mut h = harness();
+        h.run(
+            r#"
+auto_off = maybe.disable_auto_if(true).disabled()
+auto_kept = maybe.disable_auto_if(false).auto()
+on_kept = on.disable_auto_if(true).enabled()
+"#,
+        )
+        .unwrap();
+        for name in ["auto_off", "auto_kept", "on_kept"] {
+            assert_eq!(h.get(name), Value::Boolean(true), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_enable_if() {
+        let mut h = harness();
+        h.run(
+            r#"
+auto_on = maybe.enable_if(true).enabled()
+auto_kept = maybe.enable_if(false).auto()
+off_kept = off.enable_if(false).disabled()
+"#,
+        )
+        .unwrap();
+        for name in ["auto_on", "auto_kept", "off_kept"] {
+            assert_eq!(h.get(name), Value::Boolean(true), "{name}");
+        }
+
+        let err = error(&mut h, "off.enable_if(true)");
+        assert!(err.contains("Feature off cannot be enabled"), "{err}");
+    }
+
+    #[test]
+    fn test_disable_if() {
+        let mut h = harness();
+        h.run(
+            r#"
+auto_off = maybe.disable_if(true).disabled()
+auto_kept = maybe.disable_if(false).auto()
+on_kept = on.disable_if(false).enabled()
+"#,
+        )
+        .unwrap();
+        for name in ["auto_off", "auto_kept", "on_kept"] {
+            assert_eq!(h.get(name), Value::Boolean(true), "{name}");
+        }
+
+        let err = error(&mut h, "on.disable_if(true, error_message: 'broken')");
+        assert!(
+            err.contains("Feature on cannot be disabled: broken"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_auto_features() {
+        let mut h = harness();
+        h.interp.set_option("auto_features", "disabled").unwrap();
+        h.run(
+            r#"
+maybe_off = get_option('maybe').disabled()
+on_kept = get_option('on').enabled()
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("maybe_off"), Value::Boolean(true));
+        assert_eq!(h.get("on_kept"), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_required_feature() {
+        let mut h = harness();
+        h.run(
+            r#"
+skipped = dependency('missing', required: off).found()
+optional = dependency('missing', required: maybe).found()
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("skipped"), Value::Boolean(false));
+        assert_eq!(h.get("optional"), Value::Boolean(false));
+
+        let err = error(&mut h, "dependency('missing', required: on)");
+        assert!(err.contains("Dependency \"missing\" not found"), "{err}");
+    }
+}
@@ -4,6 +4,7 @@ use alloc::vec::Vec;
 use hashbrown::HashMap;
 
 use crate::interpreter::builtins::filesystem::filesystem;
+use crate::interpreter::builtins::pkgconfig::pkgconfig;
 use crate::interpreter::{
     Interpreter, InterpreterError, MesonObject as _, Value, bail_runtime_error, bail_type_error,
 };
@@ -19,6 +20,7 @@ pub fn import(
 
     match module_name.as_str() {
         "fs" => Ok(filesystem().into_object()),
+        "pkgconfig" => Ok(pkgconfig().into_object()),
         _ => bail_runtime_error!("No module named '{module_name}'"),
     }
 }
@@ -5,6 +5,8 @@ use hashbrown::HashMap;
 
 use super::builtin_impl;
 use crate::interpreter::builtins::files::files_impl;
+use crate::interpreter::builtins::utils::flatten;
+use crate::interpreter::error::bail_type_error;
 use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
 use crate::path::Path;
 
@@ -29,3 +31,24 @@ pub fn include_directories(
     let inc_dirs = IncludeDirectories { dirs };
     Ok(inc_dirs.into_object())
 }
+
+/// Reads an `include_directories:` keyword argument, whose elements are
+/// paths relative to the current directory or include_directories objects
+pub(super) fn include_dirs_impl(
+    value: Option<&Value>,
+    interp: &Interpreter,
+) -> Result<Vec<Path>, InterpreterError> {
+    let mut dirs = Vec::new();
+    for dir in flatten(&value) {
+        if let Ok(s) = dir.as_string() {
+            dirs.push(interp.current_dir.join(s));
+        } else if let Ok(inc) = dir.as_object::<IncludeDirectories>() {
+            dirs.extend(inc.dirs.iter().cloned());
+        } else {
+            bail_type_error!(
+                "Expected elements of 'include_directories' to be strings or include_directories objects"
+            );
+        }
+    }
+    Ok(dirs)
+}
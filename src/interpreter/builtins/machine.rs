@@ -1,23 +1,37 @@
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 use hashbrown::HashMap;
 
 use super::builtin_impl;
-use crate::interpreter::error::ErrorContext;
+use crate::interpreter::error::{ErrorContext, bail_runtime_error};
 use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
+use crate::machine_file::MachineValue;
 use crate::runtime::MachineInfo;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Machine {
-    system: String,
-    cpu_family: String,
+    pub(super) system: String,
+    pub(super) cpu_family: String,
     cpu: String,
     endian: String,
+    /// `None` when the machine file does not give it and the system is not
+    /// one whose kernel is known
+    kernel: Option<String>,
+    subsystem: String,
 }
 
 impl MesonObject for Machine {
-    builtin_impl!(system, cpu_family, cpu, endian);
+    builtin_impl!(
+        system,
+        cpu_family,
+        cpu,
+        endian,
+        is_big_endian,
+        kernel,
+        subsystem
+    );
 }
 
 impl Machine {
@@ -56,40 +70,305 @@ impl Machine {
     ) -> Result<Value, InterpreterError> {
         Ok(Value::String(self.endian.clone()))
     }
+
+    /// Whether the machine stores the most significant byte first
+    fn is_big_endian(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(self.endian == "big"))
+    }
+
+    fn kernel(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        match &self.kernel {
+            Some(kernel) => Ok(Value::String(kernel.clone())),
+            None => bail_runtime_error!(
+                "Kernel not defined or could not be autodetected for system '{}'",
+                self.system
+            ),
+        }
+    }
+
+    fn subsystem(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::String(self.subsystem.clone()))
+    }
 }
 
-pub fn build_machine(interp: &Interpreter) -> Result<Machine, InterpreterError> {
-    let MachineInfo {
-        system,
+impl Machine {
+    /// The Debian multiarch tuple of a Linux machine, naming its library
+    /// directory under `/usr/lib`
+    pub(super) fn multiarch(&self) -> String {
+        let little = self.endian == "little";
+        match (self.cpu_family.as_str(), little) {
+            ("x86", _) => "i386-linux-gnu".into(),
+            ("arm", _) => "arm-linux-gnueabihf".into(),
+            ("ppc", _) => "powerpc-linux-gnu".into(),
+            ("ppc64", true) => "powerpc64le-linux-gnu".into(),
+            ("ppc64", false) => "powerpc64-linux-gnu".into(),
+            ("mips", true) => "mipsel-linux-gnu".into(),
+            ("mips64", true) => "mips64el-linux-gnuabi64".into(),
+            ("mips64", false) => "mips64-linux-gnuabi64".into(),
+            (family, _) => format!("{family}-linux-gnu"),
+        }
+    }
+}
+
+impl From<MachineInfo> for Machine {
+    fn from(info: MachineInfo) -> Self {
+        Machine {
+            cpu_family: cpu_family(&info.cpu).into(),
+            kernel: info
+                .kernel
+                .or_else(|| kernel(&info.system).map(String::from)),
+            subsystem: info
+                .subsystem
+                .unwrap_or_else(|| subsystem(&info.system).into()),
+            system: info.system,
+            cpu: info.cpu,
+            endian: info.endian,
+        }
+    }
+}
+
+/// Maps a CPU name to its family, as upstream Meson does when detecting the
+/// build machine
+fn cpu_family(cpu: &str) -> &str {
+    let cpu_lower = cpu.to_ascii_lowercase();
+    match cpu_lower.as_str() {
+        c if c.starts_with('i') && c.ends_with("86") => "x86",
+        "bepc" | "x86" => "x86",
+        "amd64" | "x64" | "i86pc" | "x86_64" => "x86_64",
+        "arm64" | "arm64e" => "aarch64",
+        c if c.starts_with("aarch64") => "aarch64",
+        c if c.starts_with("arm") || c.starts_with("earm") || c.starts_with("thumb") => "arm",
+        c if c.starts_with("powerpc64") || c.starts_with("ppc64") => "ppc64",
+        "powerpc" | "ppc" | "powerpcle" | "ppcle" => "ppc",
+        "sun4u" | "sun4v" | "sparc64" => "sparc64",
+        c if c.starts_with("mips") && c.contains("64") => "mips64",
+        c if c.starts_with("mips") => "mips",
+        "ip30" | "ip35" => "mips64",
+        c if c.starts_with("riscv64") || c.starts_with("rv64") => "riscv64",
+        c if c.starts_with("riscv32") || c.starts_with("rv32") => "riscv32",
+        c if c.starts_with("loongarch64") => "loongarch64",
+        c if c.starts_with("s390x") => "s390x",
+        c if c.starts_with("m68k") => "m68k",
+        c if c.starts_with("sh4") => "sh4",
+        _ => cpu,
+    }
+}
+
+/// The kernel a system runs on, if it is one upstream Meson knows
+fn kernel(system: &str) -> Option<&str> {
+    Some(match system {
+        "windows" | "cygwin" => "nt",
+        "darwin" | "macos" | "ios" | "tvos" | "watchos" | "visionos" => "xnu",
+        "android" | "linux" => "linux",
+        "sunos" => "solaris",
+        "freebsd" | "openbsd" | "netbsd" | "dragonfly" | "haiku" | "gnu" => system,
+        _ => return None,
+    })
+}
+
+/// The subsystem of a system, which only differs from it on Apple platforms
+fn subsystem(system: &str) -> &str {
+    match system {
+        "darwin" => "macos",
+        system => system,
+    }
+}
+
+/// Reads a `[build_machine]`, `[host_machine]` or `[target_machine]`
+/// section, preferring the cross file over the native file
+fn from_machine_files(
+    interp: &Interpreter,
+    section_name: &str,
+) -> Result<Option<Machine>, InterpreterError> {
+    let files = [&interp.machine_files.cross, &interp.machine_files.native];
+    let Some(section) = files
+        .into_iter()
+        .flatten()
+        .find_map(|file| file.section(section_name))
+    else {
+        return Ok(None);
+    };
+
+    let entry = |key: &str| -> Result<Option<String>, InterpreterError> {
+        match section.get(key) {
+            Some(MachineValue::String(s)) => Ok(Some(s.clone())),
+            Some(_) => bail_runtime_error!("Expected '{key}' in [{section_name}] to be a string"),
+            None => Ok(None),
+        }
+    };
+    let required = |key: &str| {
+        entry(key)?.with_context_runtime(|| format!("Missing '{key}' in [{section_name}]"))
+    };
+
+    let endian = required("endian")?;
+    if endian != "little" && endian != "big" {
+        bail_runtime_error!(
+            "Invalid endian '{endian}' in [{section_name}], allowed values are: little, big"
+        );
+    }
+
+    let cpu = required("cpu")?;
+    let mut machine = Machine::from(MachineInfo {
+        system: required("system")?,
         cpu,
         endian,
-    } = interp
+        kernel: entry("kernel")?,
+        subsystem: entry("subsystem")?,
+    });
+    if let Some(family) = entry("cpu_family")? {
+        machine.cpu_family = family;
+    }
+
+    Ok(Some(machine))
+}
+
+pub fn build_machine(interp: &Interpreter) -> Result<Machine, InterpreterError> {
+    if let Some(machine) = from_machine_files(interp, "build_machine")? {
+        return Ok(machine);
+    }
+
+    let info = interp
         .os
         .build_machine()
         .context_runtime("Failed to get build machine info")?;
 
-    Ok(Machine {
-        system,
-        cpu_family: cpu.clone(),
-        cpu,
-        endian,
-    })
+    Ok(Machine::from(info))
 }
 
 pub fn host_machine(interp: &Interpreter) -> Result<Machine, InterpreterError> {
-    let MachineInfo {
-        system,
-        cpu,
-        endian,
-    } = interp
+    if let Some(machine) = from_machine_files(interp, "host_machine")? {
+        return Ok(machine);
+    }
+
+    let info = interp
         .os
         .host_machine()
         .context_runtime("Failed to get host machine info")?;
 
-    Ok(Machine {
-        system,
-        cpu_family: cpu.clone(),
-        cpu,
-        endian,
-    })
+    Ok(Machine::from(info))
+}
+
+/// The machine a compiler being built generates code for, which is the host
+/// machine unless the cross file has a `[target_machine]` section
+pub fn target_machine(interp: &Interpreter) -> Result<Machine, InterpreterError> {
+    match from_machine_files(interp, "target_machine")? {
+        Some(machine) => Ok(machine),
+        None => host_machine(interp),
+    }
+}
+
+/// Whether binaries built for the host machine run on the build machine.
+/// Cross files can decide with the `needs_exe_wrapper` property.
+pub fn can_run_host_binaries(interp: &Interpreter) -> Result<bool, InterpreterError> {
+    let Some(cross) = &interp.machine_files.cross else {
+        return Ok(true);
+    };
+
+    match cross.get("properties", "needs_exe_wrapper") {
+        Some(MachineValue::Boolean(needs_wrapper)) => return Ok(!needs_wrapper),
+        Some(_) => bail_runtime_error!("Expected the 'needs_exe_wrapper' property to be a boolean"),
+        None => {}
+    }
+
+    let build = build_machine(interp)?;
+    let host = host_machine(interp)?;
+    let same_family = build.cpu_family == host.cpu_family
+        || (build.cpu_family == "x86_64" && host.cpu_family == "x86");
+
+    Ok(build.system == host.system && same_family)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_utils::{Harness, MockRuntime};
+
+    #[test]
+    fn test_cpu_family() {
+        for (cpu, family) in [
+            ("i686", "x86"),
+            ("x86_64", "x86_64"),
+            ("amd64", "x86_64"),
+            ("aarch64_be", "aarch64"),
+            ("arm64", "aarch64"),
+            ("armv7m", "arm"),
+            ("thumbv6m", "arm"),
+            ("ppc64le", "ppc64"),
+            ("mips64el", "mips64"),
+            ("mipsel", "mips"),
+            ("riscv32imac", "riscv32"),
+            ("riscv64", "riscv64"),
+            ("xtensa", "xtensa"),
+        ] {
+            assert_eq!(cpu_family(cpu), family, "{cpu}");
+        }
+    }
+
+    #[test]
+    fn test_multiarch() {
+        for (cpu, endian, multiarch) in [
+            ("x86_64", "little", "x86_64-linux-gnu"),
+            ("i686", "little", "i386-linux-gnu"),
+            ("aarch64", "little", "aarch64-linux-gnu"),
+            ("armv7l", "little", "arm-linux-gnueabihf"),
+            ("ppc64le", "little", "powerpc64le-linux-gnu"),
+            ("ppc64", "big", "powerpc64-linux-gnu"),
+            ("mips64el", "little", "mips64el-linux-gnuabi64"),
+            ("mipsel", "little", "mipsel-linux-gnu"),
+            ("mips", "big", "mips-linux-gnu"),
+            ("riscv64", "little", "riscv64-linux-gnu"),
+            ("s390x", "big", "s390x-linux-gnu"),
+        ] {
+            let machine = Machine::from(MachineInfo {
+                system: "linux".into(),
+                cpu: cpu.into(),
+                endian: endian.into(),
+                kernel: None,
+                subsystem: None,
+            });
+            assert_eq!(machine.multiarch(), multiarch, "{cpu}");
+        }
+    }
+
+    #[test]
+    fn test_kernel() {
+        let cross = "[host_machine]\nsystem = 'plan9'\ncpu = 'x86_64'\nendian = 'little'\n";
+        let mut h = Harness::with_machine_files(MockRuntime::default(), Some(cross), None);
+        h.run("build = build_machine.kernel()").unwrap();
+        assert_eq!(h.get("build"), Value::String("linux".into()));
+
+        let err = h.run("host_machine.kernel()").unwrap_err();
+        let err = err.root().to_string();
+        assert!(err.contains("Kernel not defined"), "{err}");
+
+        let cross = "[host_machine]\nsystem = 'plan9'\nkernel = 'plan9'\ncpu = 'x86_64'\nendian = 'little'\n";
+        let mut h = Harness::with_machine_files(MockRuntime::default(), Some(cross), None);
+        h.run("host = host_machine.kernel()").unwrap();
+        assert_eq!(h.get("host"), Value::String("plan9".into()));
+    }
+
+    #[test]
+    fn test_is_big_endian() {
+        let cross = "[host_machine]\nsystem = 'linux'\ncpu_family = 'ppc64'\ncpu = 'ppc64'\nendian = 'big'\n";
+        let mut h = Harness::with_machine_files(MockRuntime::default(), Some(cross), None);
+        h.run("host = host_machine.is_big_endian()\nbuild = build_machine.is_big_endian()\n")
+            .unwrap();
+        assert_eq!(h.get("host"), Value::Boolean(true));
+        assert_eq!(h.get("build"), Value::Boolean(false));
+    }
 }
@@ -4,15 +4,20 @@ use alloc::vec::Vec;
 use hashbrown::HashMap;
 
 use super::builtin_impl;
-use crate::interpreter::builtins::compiler::get_compiler;
+use crate::interpreter::builtins::compiler::{Compiler, Language, get_compiler};
 use crate::interpreter::builtins::version::version;
+use crate::interpreter::error::{ErrorContext as _, bail_runtime_error};
 use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
+use crate::machine_file::MachineValue;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Meson {
     pub project_name: String,
     pub project_version: String,
     pub project_args: HashMap<String, Vec<String>>,
+    /// The compilers added by `project()` and `add_languages()`, for the
+    /// host machine or, if native, the build machine
+    pub compilers: HashMap<(Language, bool), Compiler>,
     is_subproject: bool,
 }
 
@@ -22,6 +27,7 @@ impl MesonObject for Meson {
         is_subproject,
         get_compiler,
         get_cross_property,
+        get_external_property,
         project_version,
         current_build_dir,
         current_source_dir
@@ -51,19 +57,34 @@ impl Meson {
         &self,
         args: Vec<Value>,
         kwargs: HashMap<String, Value>,
-        interp: &mut Interpreter,
+        _interp: &mut Interpreter,
     ) -> Result<Value, InterpreterError> {
-        get_compiler(args, kwargs, interp)
+        get_compiler(&self.compilers, args, kwargs)
     }
 
     fn get_cross_property(
         &self,
         args: Vec<Value>,
         _kwargs: HashMap<String, Value>,
-        _interp: &mut Interpreter,
+        interp: &mut Interpreter,
     ) -> Result<Value, InterpreterError> {
-        // Return the default value (second argument)
-        Ok(args.get(1).cloned().unwrap_or(Value::None))
+        property(&args, false, interp)
+    }
+
+    fn get_external_property(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let native = kwargs
+            .get("native")
+            .map(Value::as_boolean)
+            .transpose()
+            .context_type("The 'native' keyword argument must be a boolean")?
+            .unwrap_or(false);
+
+        property(&args, native, interp)
     }
 
     fn project_version(
@@ -94,11 +115,104 @@ impl Meson {
     }
 }
 
+/// Looks up a key of the `[properties]` section, returning the fallback given
+/// as the second argument if it is missing
+fn property(args: &[Value], native: bool, interp: &Interpreter) -> Result<Value, InterpreterError> {
+    let name = args
+        .first()
+        .context_type("Expected a string as the first argument")?
+        .as_string()
+        .context_type("Expected a string as the first argument")?;
+
+    let value = interp
+        .machine_files
+        .get(native)
+        .and_then(|file| file.get("properties", name));
+
+    match (value, args.get(1)) {
+        (Some(value), _) => Ok(Value::from(value)),
+        (None, Some(fallback)) => Ok(fallback.clone()),
+        (None, None) => {
+            let machine = if native { "build" } else { "host" };
+            bail_runtime_error!("Unknown property for {machine} machine: {name}")
+        }
+    }
+}
+
+impl From<&MachineValue> for Value {
+    fn from(value: &MachineValue) -> Self {
+        match value {
+            MachineValue::String(s) => Value::String(s.clone()),
+            MachineValue::Integer(i) => Value::Integer(*i),
+            MachineValue::Boolean(b) => Value::Boolean(*b),
+            MachineValue::Array(items) => Value::Array(items.iter().map(Value::from).collect()),
+        }
+    }
+}
+
 pub fn meson() -> Meson {
     Meson {
         project_name: "".into(),
         project_version: "0.0.0".into(),
         project_args: HashMap::new(),
+        compilers: HashMap::new(),
         is_subproject: false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString as _;
+
+    use crate::interpreter::Value;
+    use crate::interpreter::test_utils::{Harness, MockRuntime};
+
+    const CROSS: &str = "[properties]\nsys_root = '/sysroot'\nneeds_exe_wrapper = true\n";
+    const NATIVE: &str = "[properties]\nsys_root = '/'\n";
+
+    #[test]
+    fn test_cross_property() {
+        let mut h = Harness::with_machine_files(MockRuntime::default(), Some(CROSS), Some(NATIVE));
+        h.run(
+            r#"
+root = meson.get_cross_property('sys_root')
+wrapper = meson.get_cross_property('needs_exe_wrapper')
+fallback = meson.get_cross_property('missing', 'default')
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("root"), Value::String("/sysroot".into()));
+        assert_eq!(h.get("wrapper"), Value::Boolean(true));
+        assert_eq!(h.get("fallback"), Value::String("default".into()));
+
+        let err = h.run("meson.get_cross_property('missing')").unwrap_err();
+        let err = err.root().to_string();
+        assert!(
+            err.contains("Unknown property for host machine: missing"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_external_property() {
+        let mut h = Harness::with_machine_files(MockRuntime::default(), Some(CROSS), Some(NATIVE));
+        h.run(
+            r#"
+host = meson.get_external_property('sys_root')
+build = meson.get_external_property('sys_root', native: true)
+fallback = meson.get_external_property('needs_exe_wrapper', false, native: true)
+"#,
+        )
+        .unwrap();
+        assert_eq!(h.get("host"), Value::String("/sysroot".into()));
+        assert_eq!(h.get("build"), Value::String("/".into()));
+        assert_eq!(h.get("fallback"), Value::Boolean(false));
+
+        let code = "meson.get_external_property('needs_exe_wrapper', native: true)";
+        let err = h.run(code).unwrap_err().root().to_string();
+        assert!(
+            err.contains("Unknown property for build machine: needs_exe_wrapper"),
+            "{err}"
+        );
+    }
+}
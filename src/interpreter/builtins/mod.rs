@@ -4,9 +4,12 @@ pub mod build_target;
 pub mod compiler;
 pub mod config_data;
 pub mod debug;
+pub mod dependency;
 pub mod dict;
+pub mod disabler;
 pub mod env;
 pub mod external_program;
+pub mod feature;
 pub mod files;
 pub mod filesystem;
 pub mod import;
@@ -16,6 +19,7 @@ pub mod join_paths;
 pub mod machine;
 pub mod meson;
 pub mod option;
+pub mod pkgconfig;
 pub mod project;
 pub mod run_result;
 pub mod string;
@@ -1,10 +1,13 @@
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 use hashbrown::HashMap;
 
-use crate::interpreter::error::{ErrorContext as _, bail_type_error};
-use crate::interpreter::{Interpreter, InterpreterError, Value};
+use super::feature::{Feature, FeatureState};
+use crate::interpreter::error::{ErrorContext as _, bail_runtime_error, bail_type_error};
+use crate::interpreter::{Interpreter, InterpreterError, MesonObject as _, Value};
+use crate::parser::{self, Parser, Statement};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum OptionType {
@@ -12,6 +15,117 @@ pub enum OptionType {
     Integer(i64, i64),   // min, max
     String(Vec<String>), // allowed values for combo
     Array(Vec<String>),  // allowed values
+    Feature,
+}
+
+impl OptionType {
+    /// Parses a value given on the command line
+    pub fn parse(&self, name: &str, value: &str) -> Result<Value, InterpreterError> {
+        let value = match self {
+            OptionType::Boolean => match value {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => bail_runtime_error!(
+                    "Invalid value '{value}' for boolean option '{name}', allowed values are: true, false"
+                ),
+            },
+            OptionType::Integer(..) => match value.parse() {
+                Ok(int) => Value::Integer(int),
+                Err(_) => {
+                    bail_runtime_error!("Invalid value '{value}' for integer option '{name}'")
+                }
+            },
+            OptionType::String(_) => Value::String(value.into()),
+            OptionType::Array(_) => Value::Array(
+                parse_array(value)
+                    .with_context_runtime(|| {
+                        format!("Invalid value '{value}' for array option '{name}'")
+                    })?
+                    .into_iter()
+                    .map(Value::String)
+                    .collect(),
+            ),
+            OptionType::Feature => {
+                let Ok(state) = value.parse() else {
+                    bail_runtime_error!(
+                        "Invalid value '{value}' for feature option '{name}', allowed values are: enabled, disabled, auto"
+                    );
+                };
+                Feature {
+                    name: name.into(),
+                    state,
+                }
+                .into_object()
+            }
+        };
+
+        self.validate(name, &value)?;
+        Ok(value)
+    }
+
+    /// Checks a value against the range or choices of the option
+    pub fn validate(&self, name: &str, value: &Value) -> Result<(), InterpreterError> {
+        match (self, value) {
+            (OptionType::Integer(min, max), Value::Integer(int)) if int < min || int > max => {
+                bail_runtime_error!(
+                    "Value {int} for integer option '{name}' is out of range, must be between {min} and {max}"
+                );
+            }
+            (OptionType::String(choices), Value::String(string))
+                if !choices.is_empty() && !choices.contains(string) =>
+            {
+                bail_runtime_error!(
+                    "Invalid value '{string}' for combo option '{name}', allowed values are: {}",
+                    choices.join(", ")
+                );
+            }
+            (OptionType::Array(choices), Value::Array(items)) => {
+                for item in items {
+                    let item = item.as_string()?;
+                    if !choices.is_empty() && !choices.iter().any(|c| c == item) {
+                        bail_runtime_error!(
+                            "Invalid value '{item}' for array option '{name}', allowed values are: {}",
+                            choices.join(", ")
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses an array option given as a comma separated list or in meson's
+/// `['a', 'b']` syntax
+fn parse_array(value: &str) -> Result<Vec<String>, InterpreterError> {
+    if !value.trim_start().starts_with('[') {
+        return Ok(value
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+            .collect());
+    }
+
+    let statements = Parser::new(value)
+        .parse()
+        .map_err(InterpreterError::ParseError)?;
+    let [statement] = statements.as_slice() else {
+        bail_runtime_error!("Expected a single array");
+    };
+    let Statement::Expression(parser::Value::Array(items)) = &statement.node else {
+        bail_runtime_error!("Expected an array");
+    };
+
+    items
+        .iter()
+        .map(|item| match item {
+            parser::Value::String(s) => Ok(s.clone()),
+            _ => bail_runtime_error!("Expected array elements to be strings"),
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -107,9 +221,25 @@ pub fn option(
 
             (Value::Array(arr_value), OptionType::Array(choices))
         }
+        "feature" => {
+            let state = value
+                .map(Value::as_string)
+                .transpose()
+                .context_type("Expected 'value' keyword argument to be a string")?
+                .unwrap_or("auto")
+                .parse::<FeatureState>()?;
+            let feature = Feature {
+                name: name.clone(),
+                state,
+            };
+
+            (feature.into_object(), OptionType::Feature)
+        }
         ty => bail_type_error!("Unsupported option type: {ty}"),
     };
 
+    r#type.validate(&name, &value)?;
+
     let opt = BuildOption {
         r#type,
         value: value.clone(),
@@ -132,8 +262,49 @@ pub fn get_option(
         .as_string()?
         .into();
 
-    match interp.options.get(&opt) {
-        Some(v) => Ok(v.value.clone()),
+    match interp.get_option(&opt) {
+        Some(v) => Ok(v),
         None => bail_type_error!("No such option: {opt}"),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_option() {
+        let strings = |items: &[&str]| {
+            Value::Array(items.iter().map(|s| Value::String((*s).into())).collect())
+        };
+        let array = OptionType::Array(vec!["a".into(), "b".into(), "c".into()]);
+        assert_eq!(array.parse("x", "a, c").unwrap(), strings(&["a", "c"]));
+        assert_eq!(
+            array.parse("x", "['b', 'a']").unwrap(),
+            strings(&["b", "a"])
+        );
+        assert_eq!(array.parse("x", "").unwrap(), strings(&[]));
+        assert!(array.parse("x", "a,d").is_err());
+        assert!(array.parse("x", "['a'").is_err());
+
+        let combo = OptionType::String(vec!["fast".into(), "slow".into()]);
+        assert_eq!(
+            combo.parse("x", "slow").unwrap(),
+            Value::String("slow".into())
+        );
+        assert!(combo.parse("x", "medium").is_err());
+
+        let int = OptionType::Integer(0, 5);
+        assert_eq!(int.parse("x", "5").unwrap(), Value::Integer(5));
+        assert!(int.parse("x", "6").is_err());
+        assert!(int.parse("x", "five").is_err());
+
+        assert_eq!(
+            OptionType::Boolean.parse("x", "false").unwrap(),
+            Value::Boolean(false)
+        );
+        assert!(OptionType::Boolean.parse("x", "yes").is_err());
+    }
+}
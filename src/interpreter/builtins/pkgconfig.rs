@@ -0,0 +1,425 @@
+//! The `pkgconfig` module, which generates `.pc` files describing the
+//! libraries a project installs
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use super::build_target::{BuildTarget, TargetType, get_dir, library_target};
+use super::builtin_impl;
+use super::dependency::{Dependency, DependencyKind, variables_impl};
+use super::utils::flatten;
+use crate::builder::ConfigureFile;
+use crate::interpreter::error::{ErrorContext as _, bail_runtime_error, bail_type_error};
+use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
+use crate::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PkgConfigModule;
+
+impl MesonObject for PkgConfigModule {
+    builtin_impl!(generate);
+}
+
+pub fn pkgconfig() -> PkgConfigModule {
+    PkgConfigModule
+}
+
+impl PkgConfigModule {
+    fn generate(
+        &self,
+        args: Vec<Value>,
+        kwargs: HashMap<String, Value>,
+        interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        let string = |key: &str| {
+            kwargs
+                .get(key)
+                .map(Value::as_string)
+                .transpose()
+                .with_context_type(|| format!("Expected '{key}' keyword argument to be a string"))
+        };
+
+        let mainlib = match args.first() {
+            Some(value) => Some(
+                library_target(value, None)?
+                    .context_type("The first argument of generate() must be a library")?,
+            ),
+            None => None,
+        };
+
+        let name = match (string("name")?, &mainlib) {
+            (Some(name), _) => String::from(name),
+            (None, Some(lib)) => lib.name.clone(),
+            (None, None) => bail_type_error!(
+                "generate() requires a 'name' keyword argument when no library is given"
+            ),
+        };
+        let description = match (string("description")?, &mainlib) {
+            (Some(description), _) => String::from(description),
+            (None, Some(lib)) => format!("{} library", lib.name),
+            (None, None) => bail_type_error!(
+                "generate() requires a 'description' keyword argument when no library is given"
+            ),
+        };
+        let version = match string("version")? {
+            Some(version) => String::from(version),
+            None => interp.meson.borrow().project_version.clone(),
+        };
+        let filebase = string("filebase")?.map_or_else(|| name.clone(), String::from);
+
+        let mut pc = PcContent {
+            name,
+            description,
+            url: string("url")?.map(String::from),
+            version,
+            ..PcContent::default()
+        };
+
+        if let Some(lib) = &mainlib {
+            pc.add_library(lib, false);
+        }
+        pc.add_libraries(&kwargs, "libraries", false)?;
+        pc.add_libraries(&kwargs, "libraries_private", true)?;
+        pc.add_requires(&kwargs, "requires", false)?;
+        pc.add_requires(&kwargs, "requires_private", true)?;
+
+        let subdirs = kwargs.get("subdirs");
+        let subdirs = flatten(&subdirs)
+            .map(Value::as_string)
+            .collect::<Result<Vec<_>, _>>()
+            .context_type("Expected elements of 'subdirs' to be strings")?;
+        if subdirs.is_empty() {
+            pc.cflags.push("-I${includedir}".into());
+        }
+        for subdir in subdirs {
+            pc.cflags.push(match subdir {
+                "." => "-I${includedir}".into(),
+                subdir => format!("-I${{includedir}}/{subdir}"),
+            });
+        }
+        for arg in flatten(&kwargs.get("extra_cflags")) {
+            let arg = arg
+                .as_string()
+                .context_type("Expected elements of 'extra_cflags' to be strings")?;
+            pc.cflags.push(arg.into());
+        }
+
+        let prefix = get_dir(interp, "prefix")?
+            .context_runtime("Could not determine installation prefix")?;
+        let libdir = get_dir(interp, "libdir")?.context_runtime("Could not determine libdir")?;
+        let includedir =
+            get_dir(interp, "includedir")?.context_runtime("Could not determine includedir")?;
+
+        let variables = variables_impl(&kwargs)?;
+        for name in ["prefix", "libdir", "includedir"] {
+            if variables.iter().any(|(key, _)| key == name) {
+                bail_runtime_error!("Variable \"{name}\" is reserved");
+            }
+        }
+        pc.variables = [
+            ("prefix".into(), prefix.as_ref().into()),
+            ("libdir".into(), prefix_relative(&libdir)),
+            ("includedir".into(), prefix_relative(&includedir)),
+        ]
+        .into_iter()
+        .chain(variables)
+        .collect();
+
+        let install_dir = match string("install_dir")? {
+            Some(dir) => prefix.join(dir),
+            None => prefix.join(&libdir).join("pkgconfig"),
+        };
+
+        let file = ConfigureFile {
+            build_dir: interp.build_dir.clone(),
+            filename: Path::from(format!("{filebase}.pc")),
+            content: pc.content(),
+            install_dir,
+            install: true,
+        };
+
+        interp.steps.pkgconfig_file(&file);
+
+        Ok(Value::None)
+    }
+}
+
+/// Refers to an installation directory through `${prefix}` unless it is
+/// absolute
+fn prefix_relative(dir: &Path) -> String {
+    if dir.as_ref().starts_with('/') {
+        dir.as_ref().into()
+    } else {
+        format!("${{prefix}}/{dir}")
+    }
+}
+
+/// The fields of a `.pc` file, with the flags of each field in order and
+/// without duplicates
+#[derive(Debug, Default)]
+struct PcContent {
+    variables: Vec<(String, String)>,
+    name: String,
+    description: String,
+    url: Option<String>,
+    version: String,
+    requires: Vec<String>,
+    requires_private: Vec<String>,
+    libs: Vec<String>,
+    libs_private: Vec<String>,
+    cflags: Vec<String>,
+}
+
+impl PcContent {
+    fn add_libraries(
+        &mut self,
+        kwargs: &HashMap<String, Value>,
+        key: &str,
+        private: bool,
+    ) -> Result<(), InterpreterError> {
+        for value in flatten(&kwargs.get(key)) {
+            if let Value::String(arg) = value {
+                push_unique(self.libs_mut(private), arg);
+            } else if let Ok(dep) = value.as_object::<Dependency>() {
+                self.add_dependency(&dep, private);
+            } else if let Some(lib) = library_target(value, None)? {
+                self.add_library(&lib, private);
+            } else {
+                bail_type_error!(
+                    "Expected elements of '{key}' to be strings, libraries or dependencies"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn add_requires(
+        &mut self,
+        kwargs: &HashMap<String, Value>,
+        key: &str,
+        private: bool,
+    ) -> Result<(), InterpreterError> {
+        let requires = if private {
+            &mut self.requires_private
+        } else {
+            &mut self.requires
+        };
+        for value in flatten(&kwargs.get(key)) {
+            if let Value::String(name) = value {
+                push_unique(requires, name);
+            } else if let Ok(dep) = value.as_object::<Dependency>()
+                && dep.kind == DependencyKind::PkgConfig
+            {
+                push_unique(requires, &dep.name);
+            } else {
+                bail_type_error!(
+                    "Expected elements of '{key}' to be strings or pkg-config dependencies"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a library of the project, along with what it links against as
+    /// private entries if it is a static library, since an archive does not
+    /// record them
+    fn add_library(&mut self, lib: &BuildTarget, private: bool) {
+        let libs = self.libs_mut(private);
+        push_unique(libs, "-L${libdir}");
+        push_unique(libs, &format!("-l{}", lib.name));
+
+        if lib.target_type == TargetType::StaticLibrary {
+            for dep in &lib.dependencies {
+                self.add_dependency(dep, true);
+            }
+            for other in &lib.link_with {
+                self.add_library(other, true);
+            }
+        }
+    }
+
+    fn add_dependency(&mut self, dep: &Dependency, private: bool) {
+        match dep.kind {
+            DependencyKind::PkgConfig if private => {
+                push_unique(&mut self.requires_private, &dep.name)
+            }
+            DependencyKind::PkgConfig => push_unique(&mut self.requires, &dep.name),
+            DependencyKind::Internal | DependencyKind::System => {
+                for lib in &dep.link_with {
+                    self.add_library(lib, private);
+                }
+                for arg in &dep.link_args {
+                    push_unique(self.libs_mut(private), arg);
+                }
+            }
+            DependencyKind::NotFound => {}
+        }
+    }
+
+    fn libs_mut(&mut self, private: bool) -> &mut Vec<String> {
+        if private {
+            &mut self.libs_private
+        } else {
+            &mut self.libs
+        }
+    }
+
+    fn content(&self) -> String {
+        let mut content = String::new();
+        for (name, value) in &self.variables {
+            content += &format!("{name}={value}\n");
+        }
+        content += "\n";
+
+        let fields = [
+            ("Name", Some(self.name.clone())),
+            ("Description", Some(self.description.clone())),
+            ("URL", self.url.clone()),
+            ("Version", Some(self.version.clone())),
+            ("Requires", Some(self.requires.join(", "))),
+            ("Requires.private", Some(self.requires_private.join(", "))),
+            ("Libs", Some(self.libs.join(" "))),
+            ("Libs.private", Some(self.libs_private.join(" "))),
+            ("Cflags", Some(self.cflags.join(" "))),
+        ];
+        for (field, value) in fields {
+            match value {
+                Some(value) if !value.is_empty() => content += &format!("{field}: {value}\n"),
+                _ => {}
+            }
+        }
+        content
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, item: &str) {
+    if !list.iter().any(|i| i == item) {
+        list.push(item.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_utils::{Harness, MockRuntime};
+
+    #[test]
+    fn test_content() {
+        let pc = PcContent {
+            variables: alloc::vec![
+                ("prefix".into(), "/usr".into()),
+                ("libdir".into(), "${prefix}/lib".into()),
+                ("includedir".into(), "${prefix}/include".into()),
+            ],
+            name: "foo".into(),
+            description: "foo library".into(),
+            version: "1.0".into(),
+            requires_private: alloc::vec!["zlib".into()],
+            libs: alloc::vec!["-L${libdir}".into(), "-lfoo".into()],
+            cflags: alloc::vec!["-I${includedir}/foo".into()],
+            ..PcContent::default()
+        };
+        assert_eq!(
+            pc.content(),
+            "prefix=/usr\nlibdir=${prefix}/lib\nincludedir=${prefix}/include\n\n\
+             Name: foo\nDescription: foo library\nVersion: 1.0\n\
+             Requires.private: zlib\nLibs: -L${libdir} -lfoo\nCflags: -I${includedir}/foo\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_variables() {
+        let mut h = Harness::new(MockRuntime::default());
+        h.run(
+            r#"
+project('t', version: '1.0')
+pkgconfig = import('pkgconfig')
+pkgconfig.generate(
+  name: 'foo',
+  description: 'Foo',
+  variables: {'zdir': '/z', 'adir': '${zdir}/x'},
+)
+"#,
+        )
+        .unwrap();
+
+        let files = h.steps.files.borrow();
+        let (filename, content) = &files[0];
+        assert_eq!(filename, "foo.pc");
+        // A variable is defined before those referring to it
+        let variables = content.lines().skip(3).take(2).collect::<Vec<_>>();
+        assert_eq!(variables, ["zdir=/z", "adir=${zdir}/x"]);
+        drop(files);
+
+        for name in ["prefix", "libdir", "includedir"] {
+            let code = format!(
+                "pkgconfig.generate(name: 'foo', description: 'Foo', variables: {{'{name}': '/x'}})"
+            );
+            let err = h.run(&code).unwrap_err().root().to_string();
+            assert!(
+                err.contains(&format!("Variable \"{name}\" is reserved")),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_library() {
+        let os = MockRuntime::default();
+        os.files.borrow_mut().insert(
+            "/usr/lib/pkgconfig/zlib.pc".into(),
+            b"Name: zlib\nDescription: zlib\nVersion: 1.3\nLibs: -lz\n".into(),
+        );
+        let mut h = Harness::new(os);
+        h.run(
+            r#"
+project('t', version: '1.0')
+pkgconfig = import('pkgconfig')
+zlib = dependency('zlib')
+m = declare_dependency(link_args: '-lm')
+util = static_library('util', 'util.c')
+foo = static_library('foo', 'foo.c', link_with: util, dependencies: [zlib, m])
+pkgconfig.generate(foo, subdirs: ['foo', '.'], requires: 'glib-2.0', libraries: '-lextra')
+bar = both_libraries('bar', 'bar.c', link_with: util, dependencies: zlib)
+pkgconfig.generate(bar, description: 'Bar', libraries: foo, install_dir: 'share/pkgconfig')
+"#,
+        )
+        .unwrap();
+
+        let files = h.steps.files.borrow();
+        let [(foo_name, foo), (bar_name, bar)] = &files[..] else {
+            panic!("{files:?}");
+        };
+        assert_eq!(foo_name, "foo.pc");
+        // What a static library links against only goes to the private fields
+        assert_eq!(
+            foo,
+            "prefix=/usr/local\nlibdir=${prefix}/lib\nincludedir=${prefix}/include\n\n\
+             Name: foo\nDescription: foo library\nVersion: 1.0\n\
+             Requires: glib-2.0\nRequires.private: zlib\n\
+             Libs: -L${libdir} -lfoo -lextra\nLibs.private: -lm -L${libdir} -lutil\n\
+             Cflags: -I${includedir}/foo -I${includedir}\n"
+        );
+        assert_eq!(bar_name, "bar.pc");
+        // The shared library of both_libraries() is used, so the private
+        // entries only come from the static library in `libraries:`
+        assert_eq!(
+            bar,
+            "prefix=/usr/local\nlibdir=${prefix}/lib\nincludedir=${prefix}/include\n\n\
+             Name: bar\nDescription: Bar\nVersion: 1.0\n\
+             Requires.private: zlib\n\
+             Libs: -L${libdir} -lbar -lfoo\nLibs.private: -lm -L${libdir} -lutil\n\
+             Cflags: -I${includedir}\n"
+        );
+        assert_eq!(
+            *h.steps.pkgconfig_dirs.borrow(),
+            [
+                Path::from("/usr/local/lib/pkgconfig"),
+                Path::from("/usr/local/share/pkgconfig")
+            ]
+        );
+    }
+}
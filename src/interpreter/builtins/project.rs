@@ -3,6 +3,7 @@ use alloc::vec::Vec;
 
 use hashbrown::HashMap;
 
+use super::add_languages::add_languages_impl;
 use crate::interpreter::builtins::utils::flatten;
 use crate::interpreter::error::ErrorContext as _;
 use crate::interpreter::{Interpreter, InterpreterError, Value};
@@ -30,9 +31,17 @@ pub fn project(
         .as_string()
         .context_type("Expected 'version' keyword argument to be a string")?;
 
-    let mut meson = interp.meson.borrow_mut();
-    meson.project_version = project_version.into();
-    meson.project_name = project_name.into();
+    {
+        let mut meson = interp.meson.borrow_mut();
+        meson.project_version = project_version.into();
+        meson.project_name = project_name.into();
+    }
+
+    // Like `add_languages()` without `native:`, except that the host
+    // compilers are always required
+    let languages = &args[1..];
+    add_languages_impl(languages, false, true, interp)?;
+    add_languages_impl(languages, true, false, interp)?;
 
     Ok(Value::None)
 }
@@ -53,7 +62,8 @@ pub fn add_project_arguments(
 
     let mut meson = interp.meson.borrow_mut();
     for lang in language {
-        meson.project_args.insert(lang.into(), arguments.clone());
+        let project_args = meson.project_args.entry(lang.into()).or_default();
+        project_args.extend(arguments.iter().cloned());
     }
     Ok(Value::None)
 }
@@ -8,19 +8,51 @@ use crate::interpreter::builtins::utils::flatten;
 use crate::interpreter::error::ErrorContext;
 use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
 use crate::path::Path;
+use crate::runtime::RunCommandOutput;
 
 #[derive(Debug, Clone, PartialEq)]
-struct RunResult {
+pub struct RunResult {
+    compiled: bool,
     stdout: String,
     stderr: String,
     returncode: i64,
 }
 
 impl MesonObject for RunResult {
-    builtin_impl!(stdout, stderr, returncode);
+    builtin_impl!(compiled, stdout, stderr, returncode);
+}
+
+impl From<RunCommandOutput> for RunResult {
+    fn from(output: RunCommandOutput) -> Self {
+        RunResult {
+            compiled: true,
+            stdout: output.stdout,
+            stderr: output.stderr,
+            returncode: output.returncode,
+        }
+    }
 }
 
 impl RunResult {
+    /// The result of `compiler.run()` for code that failed to compile
+    pub fn not_compiled() -> Self {
+        RunResult {
+            compiled: false,
+            stdout: "UNDEFINED".into(),
+            stderr: "UNDEFINED".into(),
+            returncode: 999,
+        }
+    }
+
+    fn compiled(
+        &self,
+        _args: Vec<Value>,
+        _kwargs: HashMap<String, Value>,
+        _interp: &mut Interpreter,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Boolean(self.compiled))
+    }
+
     fn stdout(
         &self,
         _args: Vec<Value>,
@@ -71,10 +103,5 @@ pub fn run_command(
         .run_command(&cmd, &arguments)
         .context_runtime("Failed to run command")?;
 
-    Ok(RunResult {
-        stdout: output.stdout,
-        stderr: output.stderr,
-        returncode: output.returncode,
-    }
-    .into_object())
+    Ok(RunResult::from(output).into_object())
 }
@@ -1,11 +1,12 @@
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::cmp::Ordering;
 
 use hashbrown::HashMap;
 
 use super::builtin_impl;
-use crate::interpreter::error::ErrorContext;
+use crate::interpreter::error::{ErrorContext, bail_runtime_error};
 use crate::interpreter::{Interpreter, InterpreterError, MesonObject, Value};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -43,3 +44,89 @@ pub fn version(version: impl AsRef<str>) -> Result<Value, InterpreterError> {
         .with_context_runtime(|| format!("Invalid version string '{version}'"))?;
     Ok(Version { version }.into_object())
 }
+
+/// Whether a version such as `1.2` or `1.2.13.1` satisfies a requirement
+/// such as `>=1.2`. A bare version requires an equal one.
+pub fn version_matches(version: &str, req: &str) -> Result<bool, InterpreterError> {
+    let trimmed = req.trim();
+    let (op, wanted) = trimmed.split_at(trimmed.find(|c| !"<>=!".contains(c)).unwrap_or(0));
+    let holds: fn(Ordering) -> bool = match op {
+        "" | "=" | "==" => Ordering::is_eq,
+        "!=" => Ordering::is_ne,
+        "<" => Ordering::is_lt,
+        "<=" => Ordering::is_le,
+        ">=" => Ordering::is_ge,
+        ">" => Ordering::is_gt,
+        _ => bail_runtime_error!("Invalid version requirement string '{req}'"),
+    };
+    let wanted = wanted.trim_start();
+    if !wanted.starts_with(|c: char| c.is_ascii_digit()) {
+        bail_runtime_error!("Invalid version requirement string '{req}'");
+    }
+    Ok(holds(compare_versions(version, wanted)))
+}
+
+/// A run of digits or letters in a version, numbers sorting after letters
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Part<'a> {
+    Alpha(&'a str),
+    Number(u64),
+}
+
+/// Compares versions as upstream Meson does, part by part, so that `1.2.1`
+/// is newer than `1.2` and `1.10` is newer than `1.9`
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    parts(a).cmp(&parts(b))
+}
+
+fn parts(version: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = version;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphanumeric()) {
+        rest = &rest[start..];
+        let digits = rest.starts_with(|c: char| c.is_ascii_digit());
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() || c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        parts.push(match digits {
+            true => Part::Number(part.parse().unwrap_or(u64::MAX)),
+            false => Part::Alpha(part),
+        });
+        rest = tail;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_matches() {
+        assert!(version_matches("1.2", ">=1.2").unwrap());
+        assert!(!version_matches("1.2.13.1", "== 1.2.13").unwrap());
+        assert!(version_matches("1.2.13", "== 1.2.13").unwrap());
+        assert!(!version_matches("1.2.1", "==1.2").unwrap());
+        assert!(version_matches("1.2", "1.2").unwrap());
+        assert!(!version_matches("1.2.1", "1.2").unwrap());
+        assert!(version_matches("1.2.1", "!=1.2").unwrap());
+        assert!(!version_matches("1.2", "!= 1.2").unwrap());
+        assert!(version_matches("1.2", "<=1.2").unwrap());
+        assert!(version_matches("1.10", ">1.9").unwrap());
+        assert!(!version_matches("1.1.9", ">=1.2").unwrap());
+        assert!(version_matches("2.0rc1", "<3").unwrap());
+        assert!(version_matches("1", "bad").is_err());
+        assert!(version_matches("1", "=<1").is_err());
+        assert!(version_matches("1", ">=").is_err());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.2.10", "1.2.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.02", "1.2"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.2a", "1.2b"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.1", "1.2rc1"), Ordering::Greater);
+    }
+}
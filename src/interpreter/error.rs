@@ -1,13 +1,105 @@
+use alloc::boxed::Box;
 use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::fmt;
 use core::fmt::Display;
 
+use crate::parser::{Location, ParseError};
+
+/// Errors raised while interpreting a build file
+///
+/// Errors that escape a statement or a function call are wrapped in
+/// `Traced`, which records the call stack from the innermost frame outwards.
 #[derive(Debug)]
 pub enum InterpreterError {
     UndefinedVariable(String),
     UndefinedFunction(String),
     TypeError(String),
     RuntimeError(String),
+    ParseError(ParseError),
+    Traced(Box<InterpreterError>, Vec<Frame>),
+}
+
+/// An entry in the interpreter call stack
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The location of the statement or call in its build file
+    pub location: Location,
+    /// The function or method being called, if this frame is a call
+    pub function: Option<String>,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location.file {
+            Some(file) => write!(f, "in {file}:{}", self.location.line)?,
+            None => write!(f, "in <string>:{}", self.location.line)?,
+        }
+        if let Some(function) = &self.function {
+            write!(f, ", in {function}()")?;
+        }
+        Ok(())
+    }
+}
+
+impl InterpreterError {
+    /// Returns the error without its backtrace
+    pub fn root(&self) -> &InterpreterError {
+        match self {
+            InterpreterError::Traced(err, _) => err,
+            err => err,
+        }
+    }
+
+    /// Returns the call stack the error propagated through, innermost frame first
+    pub fn backtrace(&self) -> &[Frame] {
+        match self {
+            InterpreterError::Traced(_, frames) => frames,
+            _ => &[],
+        }
+    }
+
+    /// Returns the source location the error was raised at, if known
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            InterpreterError::ParseError(err) => err.location(),
+            InterpreterError::Traced(err, frames) => err
+                .location()
+                .or_else(|| frames.first().map(|frame| &frame.location)),
+            _ => None,
+        }
+    }
+
+    /// Records the statement the error was raised in, unless a more precise
+    /// location is already known
+    pub(crate) fn in_statement(self, location: impl FnOnce() -> Location) -> Self {
+        if self.location().is_some() {
+            return self;
+        }
+        self.push_frame(Frame {
+            location: location(),
+            function: None,
+        })
+    }
+
+    /// Records the function or method call the error propagated through
+    pub(crate) fn in_call(self, function: &str, location: impl FnOnce() -> Location) -> Self {
+        self.push_frame(Frame {
+            location: location(),
+            function: Some(function.into()),
+        })
+    }
+
+    fn push_frame(self, frame: Frame) -> Self {
+        match self {
+            InterpreterError::Traced(err, mut frames) => {
+                frames.push(frame);
+                InterpreterError::Traced(err, frames)
+            }
+            err => InterpreterError::Traced(Box::new(err), vec![frame]),
+        }
+    }
 }
 
 macro_rules! bail_type_error {
@@ -97,8 +189,53 @@ impl fmt::Display for InterpreterError {
             InterpreterError::UndefinedFunction(name) => write!(f, "Undefined function: {}", name),
             InterpreterError::TypeError(msg) => write!(f, "Type error: {}", msg),
             InterpreterError::RuntimeError(msg) => write!(f, "Runtime error: {}", msg),
+            InterpreterError::ParseError(err) => write!(f, "Parse error: {}", err),
+            InterpreterError::Traced(err, frames) => {
+                let mut frames = frames.iter();
+                if matches!(**err, InterpreterError::ParseError(_)) {
+                    write!(f, "{err}")?;
+                } else if let Some(frame) = frames.next() {
+                    write!(f, "{frame}: {err}\n{}", frame.location.snippet())?;
+                }
+                for frame in frames {
+                    write!(f, "\n  {frame}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
 impl core::error::Error for InterpreterError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Span;
+    use crate::path::Path;
+
+    #[test]
+    fn test_backtrace_display() {
+        let inner = "\n\ncc.has_function('foo')\n";
+        let outer = "project('x')\nsubdir('libc')\n";
+
+        let err = InterpreterError::RuntimeError("boom".into())
+            .in_call("has_function", || {
+                Location::new(inner, Span::new(5, 22)).with_file(Path::from("libc/meson.build"))
+            })
+            .in_statement(|| unreachable!("the error is already located"))
+            .in_call("subdir", || {
+                Location::new(outer, Span::new(13, 27)).with_file(Path::from("meson.build"))
+            });
+
+        assert!(matches!(err.root(), InterpreterError::RuntimeError(_)));
+        assert_eq!(err.location().map(|l| l.line), Some(3));
+        assert_eq!(err.backtrace().len(), 2);
+        assert_eq!(
+            err.to_string(),
+            "in libc/meson.build:3, in has_function(): Runtime error: boom\n \
+             3 | cc.has_function('foo')\n   |    ^\n  \
+             in meson.build:2, in subdir()"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! A runtime and builder for interpreter tests, keeping files in memory,
+//! running commands through a handler and recording the messages printed and
+//! the build steps generated
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+
+use anyhow::bail;
+use hashbrown::HashMap;
+
+use super::{Interpreter, InterpreterError, Value};
+use crate::builder::{BuildTarget, Builder, ConfigureFile};
+use crate::machine_file::{MachineFile, MachineFiles};
+use crate::path::Path;
+use crate::runtime::{CompilerInfo, MachineInfo, Result, RunCommandOutput, Runtime, TempDir};
+
+/// A command given to the [`MockRuntime`], with the content of the file it
+/// compiles, if any
+pub struct Invocation<'a> {
+    pub cmd: &'a str,
+    pub args: &'a [&'a str],
+    pub source: Option<String>,
+}
+
+type Handler = Box<dyn Fn(&Invocation) -> Result<RunCommandOutput>>;
+
+pub struct MockRuntime {
+    pub files: RefCell<HashMap<String, Vec<u8>>>,
+    pub printed: RefCell<Vec<String>>,
+    pub machine: MachineInfo,
+    /// The number of commands given to each call of `run_commands`
+    pub batches: RefCell<Vec<usize>>,
+    handler: Handler,
+    tempdirs: Cell<usize>,
+}
+
+impl MockRuntime {
+    /// A runtime running commands through `handler`. The stdout of a command
+    /// given `-o` is written to the output file, as a preprocessor would.
+    pub fn new(handler: impl Fn(&Invocation) -> Result<RunCommandOutput> + 'static) -> Self {
+        Self {
+            files: Default::default(),
+            printed: Default::default(),
+            machine: MachineInfo {
+                system: "linux".into(),
+                cpu: "x86_64".into(),
+                endian: "little".into(),
+                kernel: None,
+                subsystem: None,
+            },
+            batches: Default::default(),
+            handler: Box::new(handler),
+            tempdirs: Cell::new(0),
+        }
+    }
+}
+
+impl Default for MockRuntime {
+    /// A runtime that fails to run any command
+    fn default() -> Self {
+        Self::new(|invocation| bail!("Unexpected command: {:?}", invocation.args))
+    }
+}
+
+/// The output of a command exiting with `returncode`
+pub fn output(returncode: i64, stdout: &str) -> RunCommandOutput {
+    RunCommandOutput {
+        stdout: stdout.into(),
+        stderr: String::new(),
+        returncode,
+    }
+}
+
+impl Runtime for MockRuntime {
+    fn print(&self, msg: &str) {
+        self.printed.borrow_mut().push(msg.into());
+    }
+
+    fn get_env(&self, _key: &str) -> Option<String> {
+        None
+    }
+
+    fn build_machine(&self) -> Result<MachineInfo> {
+        Ok(self.machine.clone())
+    }
+
+    fn host_machine(&self) -> Result<MachineInfo> {
+        Ok(self.machine.clone())
+    }
+
+    fn is_file(&self, path: &Path) -> Result<bool> {
+        Ok(self.files.borrow().contains_key(path.as_ref()))
+    }
+
+    fn is_dir(&self, _path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool> {
+        self.is_file(path)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        match self.files.borrow().get(path.as_ref()) {
+            Some(data) => Ok(data.clone()),
+            None => bail!("No such file: {path}"),
+        }
+    }
+
+    fn write_file(&self, path: &Path, data: &[u8]) -> Result<()> {
+        self.files
+            .borrow_mut()
+            .insert(path.to_string(), data.into());
+        Ok(())
+    }
+
+    fn tempdir(&self) -> Result<TempDir> {
+        let n = self.tempdirs.get();
+        self.tempdirs.set(n + 1);
+        Ok(TempDir::new(Path::from(alloc::format!("/tmp/{n}")), ()))
+    }
+
+    fn get_compiler(&self, _lang: &str) -> Result<CompilerInfo> {
+        Ok(CompilerInfo {
+            bin: Path::from("cc"),
+            flags: Vec::new(),
+        })
+    }
+
+    fn find_program(&self, name: &Path, _pwd: &Path) -> Result<Path> {
+        bail!("Program not found: {name}")
+    }
+
+    fn run_command(&self, cmd: &Path, args: &[&str]) -> Result<RunCommandOutput> {
+        let source = args.iter().find_map(|arg| {
+            let data = self.files.borrow().get(*arg)?.clone();
+            Some(String::from_utf8_lossy(&data).into_owned())
+        });
+        let cmd = cmd.as_ref();
+        let result = (self.handler)(&Invocation { cmd, args, source })?;
+
+        if let Some(i) = args.iter().position(|arg| *arg == "-o")
+            && let Some(out) = args.get(i + 1)
+        {
+            let data = result.stdout.clone().into_bytes();
+            self.files.borrow_mut().insert(String::from(*out), data);
+        }
+        Ok(result)
+    }
+
+    fn run_commands(&self, commands: &[(&Path, &[&str])]) -> Vec<Result<RunCommandOutput>> {
+        self.batches.borrow_mut().push(commands.len());
+        commands
+            .iter()
+            .map(|(cmd, args)| self.run_command(cmd, args))
+            .collect()
+    }
+}
+
+/// A builder recording the steps it is given
+#[derive(Default)]
+pub struct MockBuilder {
+    pub targets: RefCell<Vec<BuildTarget>>,
+    /// The name and content of each configured or generated file
+    pub files: RefCell<Vec<(String, String)>>,
+    /// The install directory of each generated `.pc` file
+    pub pkgconfig_dirs: RefCell<Vec<Path>>,
+}
+
+impl Builder for MockBuilder {
+    fn build_static_library(&self, target: &BuildTarget) {
+        self.targets.borrow_mut().push(target.clone());
+    }
+
+    fn build_shared_library(&self, target: &BuildTarget) {
+        self.targets.borrow_mut().push(target.clone());
+    }
+
+    fn build_executable(&self, target: &BuildTarget) {
+        self.targets.borrow_mut().push(target.clone());
+    }
+
+    fn configure_file(&self, file: &ConfigureFile) {
+        let entry = (file.filename.to_string(), file.content.clone());
+        self.files.borrow_mut().push(entry);
+    }
+
+    fn pkgconfig_file(&self, file: &ConfigureFile) {
+        self.configure_file(file);
+        self.pkgconfig_dirs
+            .borrow_mut()
+            .push(file.install_dir.clone());
+    }
+
+    fn install_headers(&self, _install_dir: &Path, _headers: &[Path]) {}
+}
+
+/// An interpreter over a [`MockRuntime`] and a [`MockBuilder`], with the
+/// built-in options defined
+pub struct Harness {
+    pub interp: Interpreter,
+    pub os: Rc<MockRuntime>,
+    pub steps: Rc<MockBuilder>,
+}
+
+impl Harness {
+    pub fn new(os: MockRuntime) -> Self {
+        Self::with_machine_files(os, None, None)
+    }
+
+    /// Configures the interpreter with the given cross and native files
+    pub fn with_machine_files(os: MockRuntime, cross: Option<&str>, native: Option<&str>) -> Self {
+        let parse = |text: &str| MachineFile::parse(text).unwrap();
+        let machine_files = MachineFiles {
+            cross: cross.map(parse),
+            native: native.map(parse),
+        };
+
+        let os = Rc::new(os);
+        let steps = Rc::new(MockBuilder::default());
+        let mut interp = Interpreter::new(
+            os.clone(),
+            steps.clone(),
+            Path::from("/src"),
+            Path::from("/build"),
+            machine_files,
+        )
+        .unwrap();
+        interp
+            .interpret_string(include_str!("../builtin-options.txt"))
+            .unwrap();
+        interp.set_machine_file_options("built-in options").unwrap();
+
+        Self { interp, os, steps }
+    }
+
+    pub fn run(&mut self, code: &str) -> core::result::Result<(), InterpreterError> {
+        self.interp.interpret_string(code)
+    }
+
+    /// The value of a variable set by the code run so far
+    pub fn get(&self, name: &str) -> Value {
+        self.interp.variables[name].clone()
+    }
+}
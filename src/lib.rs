@@ -3,23 +3,36 @@
 extern crate alloc;
 
 pub mod builder;
+pub mod formatter;
 mod interpreter;
 mod machine_file;
-mod parser;
+pub mod parser;
 pub mod path;
+mod pkg_config;
 pub mod runtime;
 
 use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::vec::Vec;
 
 use hashbrown::HashMap;
 
+pub use crate::interpreter::InterpreterError;
+pub use crate::interpreter::error::Frame;
+use crate::machine_file::{MachineFile, MachineFiles};
+pub use crate::parser::{Location, ParseError};
 use crate::path::Path;
 
+/// Where the compiler check cache is saved, relative to the build directory
+const CHECK_CACHE_FILE: &str = "picomeson-checks.cache";
+
 pub struct Meson {
     os: Rc<dyn runtime::Runtime>,
     steps: Rc<dyn builder::Builder>,
     options: HashMap<String, String>,
+    cross_files: Vec<Path>,
+    native_files: Vec<Path>,
+    cache_checks: bool,
 }
 
 impl Meson {
@@ -27,7 +40,14 @@ impl Meson {
         let os = Rc::new(os);
         let steps = Rc::new(steps);
         let options = Default::default();
-        Self { os, steps, options }
+        Self {
+            os,
+            steps,
+            options,
+            cross_files: Vec::new(),
+            native_files: Vec::new(),
+            cache_checks: false,
+        }
     }
 
     pub fn option(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
@@ -35,6 +55,43 @@ impl Meson {
         self
     }
 
+    /// Adds a cross file describing the host machine. When several are
+    /// given they are read as one file, in order.
+    pub fn cross_file(&mut self, path: impl AsRef<str>) -> &mut Self {
+        self.cross_files.push(Path::from(path.as_ref()));
+        self
+    }
+
+    /// Adds a native file describing the build machine
+    pub fn native_file(&mut self, path: impl AsRef<str>) -> &mut Self {
+        self.native_files.push(Path::from(path.as_ref()));
+        self
+    }
+
+    /// Saves the results of compiler checks in the build directory, and
+    /// reuses them on the next build instead of running the compiler again
+    pub fn cache_compiler_checks(&mut self, enable: bool) -> &mut Self {
+        self.cache_checks = enable;
+        self
+    }
+
+    fn load_machine_file(&self, paths: &[Path]) -> anyhow::Result<Option<MachineFile>> {
+        if paths.is_empty() {
+            return Ok(None);
+        }
+
+        let mut content = String::new();
+        for path in paths {
+            let data = self.os.read_file(path)?;
+            content.push_str(&String::from_utf8_lossy(&data));
+            content.push('\n');
+        }
+
+        let machine_file = MachineFile::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse machine file: {e}"))?;
+        Ok(Some(machine_file))
+    }
+
     pub fn build(
         &self,
         src_dir: impl AsRef<str>,
@@ -43,19 +100,35 @@ impl Meson {
         let src_dir = Path::from(src_dir.as_ref());
         let build_dir = Path::from(build_dir.as_ref());
 
+        let machine_files = MachineFiles {
+            cross: self.load_machine_file(&self.cross_files)?,
+            native: self.load_machine_file(&self.native_files)?,
+        };
+
+        let check_cache_path = build_dir.join(CHECK_CACHE_FILE);
+
         let mut interp = interpreter::Interpreter::new(
             self.os.clone(),
             self.steps.clone(),
             src_dir.clone(),
             build_dir,
+            machine_files,
         )?;
 
+        if self.cache_checks
+            && let Ok(data) = self.os.read_file(&check_cache_path)
+        {
+            interp.load_check_cache(&data);
+        }
+
         interp.interpret_string(include_str!("builtin-options.txt"))?;
+        interp.set_machine_file_options("built-in options")?;
 
         let meson_options_path = src_dir.join("meson_options.txt");
         if self.os.exists(&meson_options_path).unwrap_or(false) {
             interp.interpret_file(&meson_options_path)?;
         }
+        interp.set_machine_file_options("project options")?;
 
         for (name, value) in &self.options {
             interp.set_option(name, value)?;
@@ -64,6 +137,12 @@ impl Meson {
         let meson_build_path = src_dir.join("meson.build");
         interp.interpret_file(&meson_build_path)?;
 
+        if self.cache_checks
+            && let Some(data) = interp.save_check_cache()
+        {
+            self.os.write_file(&check_cache_path, data.as_bytes())?;
+        }
+
         Ok(())
     }
 }
@@ -1,5 +1,71 @@
-#![allow(unused_imports)]
+use alloc::string::String;
+use alloc::vec::Vec;
 
 mod parser;
 
 pub use parser::{MachineFile, MachineValue};
+
+/// The machine files a build was configured with
+#[derive(Debug, Clone, Default)]
+pub struct MachineFiles {
+    /// Describes the host machine when cross compiling
+    pub cross: Option<MachineFile>,
+    /// Describes the build machine
+    pub native: Option<MachineFile>,
+}
+
+impl MachineFiles {
+    /// The file describing the host machine, or the build machine if `native`
+    ///
+    /// Without a cross file the host is the build machine, so the native
+    /// file describes both.
+    pub fn get(&self, native: bool) -> Option<&MachineFile> {
+        match &self.cross {
+            Some(cross) if !native => Some(cross),
+            _ => self.native.as_ref(),
+        }
+    }
+
+    /// A binary from the `[binaries]` section, split into the program and
+    /// the arguments that follow it
+    pub fn binary(&self, name: &str, native: bool) -> Option<(&str, Vec<String>)> {
+        let (first, rest) = match self.get(native)?.get("binaries", name)? {
+            MachineValue::Array(items) => items.split_first()?,
+            value => (value, &[][..]),
+        };
+        let args = rest.iter().map(MachineValue::coerce_string).collect();
+        Some((first.as_string()?, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_binary() {
+        let cross = MachineFile::parse(
+            r#"
+[binaries]
+c = ['clang', '--target=arm-none-eabi']
+ar = 'llvm-ar'
+"#,
+        )
+        .unwrap();
+        let native = MachineFile::parse("[binaries]\nc = 'gcc'\n").unwrap();
+
+        let files = MachineFiles {
+            cross: Some(cross),
+            native: Some(native),
+        };
+        assert_eq!(
+            files.binary("c", false),
+            Some(("clang", vec!["--target=arm-none-eabi".into()]))
+        );
+        assert_eq!(files.binary("ar", false), Some(("llvm-ar", vec![])));
+        assert_eq!(files.binary("c", true), Some(("gcc", vec![])));
+        assert_eq!(files.binary("ar", true), None);
+    }
+}
@@ -1,8 +1,8 @@
 #![allow(dead_code)]
 
 use alloc::string::{String, ToString};
-use alloc::vec;
 use alloc::vec::Vec;
+use alloc::{format, vec};
 
 use hashbrown::HashMap;
 
@@ -30,7 +30,9 @@ impl MachineValue {
                 }
                 Ok(MachineValue::Array(result))
             }
-            _ => Err(ParseError::UnexpectedToken),
+            _ => Err(ParseError::InvalidExpression(format!(
+                "Unsupported value in machine file: {value:?}"
+            ))),
         }
     }
 
@@ -220,13 +222,17 @@ impl MachineFileParser {
         let mut statements = parser.parse()?;
 
         if statements.len() != 1 {
-            return Err(ParseError::UnexpectedToken);
+            return Err(ParseError::InvalidExpression(
+                "Expected a single value".into(),
+            ));
         }
 
-        if let Statement::Expression(expr) = statements.swap_remove(0) {
+        if let Statement::Expression(expr) = statements.swap_remove(0).node {
             Ok(expr)
         } else {
-            Err(ParseError::UnexpectedToken)
+            Err(ParseError::InvalidExpression(
+                "Expected a value, found a statement".into(),
+            ))
         }
     }
 }
@@ -251,7 +257,9 @@ impl MachineFile {
                 } else if let Some(val) = self.get(section, name) {
                     Ok(val.to_value())
                 } else {
-                    Err(ParseError::UnexpectedToken)
+                    Err(ParseError::InvalidExpression(format!(
+                        "Undefined variable '{name}'"
+                    )))
                 }
             }
             Value::BinaryOp(left, op, right) => {
@@ -282,7 +290,9 @@ impl MachineFile {
                                 result.extend(b.iter().cloned());
                                 Ok(Value::Array(result))
                             }
-                            _ => Err(ParseError::UnexpectedToken),
+                            _ => Err(ParseError::InvalidExpression(
+                                "Cannot add incompatible types".into(),
+                            )),
                         }
                     }
                     BinaryOperator::Div => {
@@ -303,13 +313,19 @@ impl MachineFile {
                                 result.push_str(b);
                                 Ok(Value::String(result))
                             }
-                            _ => Err(ParseError::UnexpectedToken),
+                            _ => Err(ParseError::InvalidExpression(
+                                "Cannot join non-string paths".into(),
+                            )),
                         }
                     }
-                    _ => Err(ParseError::UnexpectedToken),
+                    _ => Err(ParseError::InvalidExpression(format!(
+                        "Unsupported operator {op:?}"
+                    ))),
                 }
             }
-            _ => Err(ParseError::UnexpectedToken),
+            _ => Err(ParseError::InvalidExpression(format!(
+                "Unsupported expression {value:?}"
+            ))),
         }
     }
 }
@@ -1,13 +1,22 @@
 use alloc::boxed::Box;
-use alloc::string::String;
-use alloc::vec;
+use alloc::string::{String, ToString as _};
 use alloc::vec::Vec;
+use core::fmt;
 
-use hashbrown::HashMap;
+use hashbrown::{DefaultHashBuilder, HashMap};
+use indexmap::IndexMap;
 
+pub mod cst;
 pub mod error;
+mod lower;
+pub mod span;
 
+use cst::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, Trivia};
 pub use error::ParseError;
+pub use span::{Location, Span, Spanned};
+
+/// The map behind dict values, which iterates in insertion order
+pub type Dict<V> = IndexMap<String, V, DefaultHashBuilder>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
@@ -16,10 +25,10 @@ pub enum Value {
     Integer(i64),
     Boolean(bool),
     Array(Vec<Value>),
-    Dict(HashMap<String, Value>),
+    Dict(Dict<Value>),
     Identifier(String),
-    FunctionCall(String, Vec<Value>, HashMap<String, Value>), // name, args, kwargs
-    MethodCall(Box<Value>, String, Vec<Value>, HashMap<String, Value>), // object, method, args, kwargs
+    FunctionCall(String, Vec<Value>, HashMap<String, Value>, Span), // name, args, kwargs, call site
+    MethodCall(Box<Value>, String, Vec<Value>, HashMap<String, Value>, Span), // object, method, args, kwargs, call site
     BinaryOp(Box<Value>, BinaryOperator, Box<Value>),
     UnaryOp(UnaryOperator, Box<Value>),
     Subscript(Box<Value>, Box<Value>),
@@ -58,22 +67,29 @@ pub enum Statement {
     Expression(Value),
     If(
         Value,
-        Vec<Statement>,
-        Vec<(Value, Vec<Statement>)>,
-        Option<Vec<Statement>>,
+        Vec<Spanned<Statement>>,
+        Vec<(Value, Vec<Spanned<Statement>>)>,
+        Option<Vec<Spanned<Statement>>>,
     ), // condition, then, elif_branches, else
-    Foreach(String, Value, Vec<Statement>),
+    Foreach(Vec<String>, Value, Vec<Spanned<Statement>>), // loop variables, iterable, body
     Break,
     Continue,
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>,
+    trivia: Vec<Vec<Trivia>>,
     pos: usize,
+    last: usize,
+    source: String,
+    tree: Vec<SyntaxElement>,
+    recovering: bool,
+    errors: Vec<ParseError>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-enum Token {
+pub enum Token {
     // Literals
     String(String),
     FormatString(String),
@@ -128,9 +144,61 @@ enum Token {
     Eof,
 }
 
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Token::String(s) => return write!(f, "string {s:?}"),
+            Token::FormatString(s) => return write!(f, "format string {s:?}"),
+            Token::Integer(i) => return write!(f, "integer {i}"),
+            Token::Identifier(name) => return write!(f, "identifier '{name}'"),
+            Token::True => "'true'",
+            Token::False => "'false'",
+            Token::If => "'if'",
+            Token::Elif => "'elif'",
+            Token::Else => "'else'",
+            Token::Endif => "'endif'",
+            Token::Foreach => "'foreach'",
+            Token::Endforeach => "'endforeach'",
+            Token::Break => "'break'",
+            Token::Continue => "'continue'",
+            Token::And => "'and'",
+            Token::Or => "'or'",
+            Token::Not => "'not'",
+            Token::In => "'in'",
+            Token::Plus => "'+'",
+            Token::Minus => "'-'",
+            Token::Star => "'*'",
+            Token::Slash => "'/'",
+            Token::Percent => "'%'",
+            Token::Eq => "'=='",
+            Token::Ne => "'!='",
+            Token::Lt => "'<'",
+            Token::Le => "'<='",
+            Token::Gt => "'>'",
+            Token::Ge => "'>='",
+            Token::Assign => "'='",
+            Token::AddAssign => "'+='",
+            Token::Question => "'?'",
+            Token::Colon => "':'",
+            Token::LeftParen => "'('",
+            Token::RightParen => "')'",
+            Token::LeftBracket => "'['",
+            Token::RightBracket => "']'",
+            Token::LeftBrace => "'{'",
+            Token::RightBrace => "'}'",
+            Token::Comma => "','",
+            Token::Dot => "'.'",
+            Token::Newline => "newline",
+            Token::Eof => "end of file",
+        };
+        f.write_str(s)
+    }
+}
+
 struct Lexer<'a> {
     chars: core::iter::Peekable<core::str::CharIndices<'a>>,
     current_pos: usize,
+    len: usize,
 }
 
 impl<'a> Lexer<'a> {
@@ -138,9 +206,15 @@ impl<'a> Lexer<'a> {
         Self {
             chars: input.char_indices().peekable(),
             current_pos: 0,
+            len: input.len(),
         }
     }
 
+    /// Byte offset of the next character to be read
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map(|(pos, _)| *pos).unwrap_or(self.len)
+    }
+
     fn peek_char(&mut self) -> Option<char> {
         self.chars.peek().map(|(_, ch)| *ch)
     }
@@ -435,15 +509,19 @@ impl<'a> Lexer<'a> {
         num_str.parse().unwrap_or(0)
     }
 
-    fn tokenize(&mut self) -> Vec<Token> {
+    fn tokenize(&mut self) -> (Vec<Token>, Vec<Span>) {
         let mut tokens = Vec::new();
+        let mut spans = Vec::new();
 
         loop {
             self.skip_whitespace();
 
+            let start = self.offset();
+
             match self.peek_char() {
                 None => {
                     tokens.push(Token::Eof);
+                    spans.push(Span::new(start, start));
                     break;
                 }
                 Some('\n') => {
@@ -623,443 +701,480 @@ impl<'a> Lexer<'a> {
                     self.next_char(); // Skip unknown characters
                 }
             }
+
+            if spans.len() < tokens.len() {
+                spans.push(Span::new(start, self.offset()));
+            }
         }
 
-        tokens
+        (tokens, spans)
     }
 }
 
-// ... (Parser implementation remains mostly the same, with these key additions)
-
 impl Parser {
     pub fn new(input: &str) -> Self {
         let mut lexer = Lexer::new(input);
-        let tokens = lexer.tokenize();
-        Parser { tokens, pos: 0 }
+        let (tokens, spans) = lexer.tokenize();
+
+        // Everything between two significant tokens becomes the leading
+        // trivia of the second one; newline tokens only matter to the parser
+        let mut trivia = Vec::with_capacity(tokens.len());
+        let mut prev_end = 0;
+        for (token, span) in tokens.iter().zip(&spans) {
+            if *token == Token::Newline {
+                trivia.push(Vec::new());
+            } else {
+                trivia.push(cst::lex_trivia(&input[prev_end..span.start]));
+                prev_end = span.end;
+            }
+        }
+
+        Parser {
+            tokens,
+            spans,
+            trivia,
+            pos: 0,
+            last: 0,
+            source: input.into(),
+            tree: Vec::new(),
+            recovering: false,
+            errors: Vec::new(),
+        }
     }
 
-    // ... (rest of the parser methods remain the same as before)
+    pub fn parse(&mut self) -> Result<Vec<Spanned<Statement>>, ParseError> {
+        let root = self.parse_cst()?;
+        Ok(lower::lower(&root))
+    }
 
-    fn dict_elements(&mut self) -> Result<HashMap<String, Value>, ParseError> {
-        let mut dict = HashMap::new();
+    /// Parses the input into a lossless concrete syntax tree
+    pub fn parse_cst(&mut self) -> Result<SyntaxNode, ParseError> {
+        self.block(|t| *t == Token::Eof, "end of file")?;
+        // The end of file token carries the trailing trivia
+        self.bump(self.tokens.len() - 1);
+        Ok(SyntaxNode {
+            kind: NodeKind::Root,
+            children: core::mem::take(&mut self.tree),
+        })
+    }
 
-        if matches!(self.peek(), Token::RightBrace) {
-            return Ok(dict);
-        }
+    /// Parses the whole input without stopping at the first syntax error
+    ///
+    /// After an error the parser skips ahead to the next statement boundary
+    /// (a newline outside of brackets, or a block keyword such as `endif`
+    /// and `endforeach`) and carries on. Returns every statement that could
+    /// be parsed together with all the errors found along the way.
+    pub fn parse_recovering(&mut self) -> (Vec<Spanned<Statement>>, Vec<ParseError>) {
+        let (root, errors) = self.parse_cst_recovering();
+        (lower::lower(&root), errors)
+    }
+
+    /// Like [`Parser::parse_recovering`], but returns the concrete syntax
+    /// tree. Skipped tokens end up in [`NodeKind::Error`] nodes, so the tree
+    /// still covers the whole input.
+    pub fn parse_cst_recovering(&mut self) -> (SyntaxNode, Vec<ParseError>) {
+        self.recovering = true;
+        // The top level block only ends at the end of file, so every error
+        // is recovered from inside it
+        let root = self.parse_cst().unwrap_or_else(|err| {
+            self.errors.push(err);
+            self.pos = self.tokens.len();
+            SyntaxNode {
+                kind: NodeKind::Error,
+                children: core::mem::take(&mut self.tree),
+            }
+        });
+        self.recovering = false;
+        (root, core::mem::take(&mut self.errors))
+    }
 
+    fn block(&mut self, is_end: impl Fn(&Token) -> bool, expected: &str) -> Result<(), ParseError> {
         loop {
-            // Meson allows both string keys and identifier keys in dict literals
-            let key = match self.peek() {
-                Token::String(s) => {
-                    let key = s.clone();
-                    self.advance();
-                    key
-                }
-                Token::Identifier(s) => {
-                    // Support for shorthand key notation (identifier as key)
-                    let key = s.clone();
-                    self.advance();
-                    key
-                }
-                Token::FormatString(s) => {
-                    let key = s.clone();
-                    self.advance();
-                    key
+            let token = self.peek();
+            if is_end(&token) {
+                break;
+            }
+            if token == Token::Eof {
+                // In recovering mode the block is closed here, keeping its
+                // statements inside it, and the missing keyword is reported
+                // where it was expected
+                if !self.recovering {
+                    return Err(self.error(expected));
                 }
-                _ => return Err(ParseError::UnexpectedToken),
-            };
-
-            self.expect(&Token::Colon)?;
-            let value = self.expression()?;
-            dict.insert(key, value);
-
-            if !self.match_token(&Token::Comma) {
+                self.errors.push(self.error(expected));
                 break;
             }
 
-            // Allow trailing comma
-            if matches!(self.peek(), Token::RightBrace) {
-                break;
+            let start = self.peek_index();
+            self.recoverable(Self::statement)?;
+            if self.pos == start {
+                // Stray block keyword, skip it to make progress. It replaces
+                // the empty error node left by the failed statement.
+                self.tree.pop();
+                let checkpoint = self.tree.len();
+                self.advance();
+                self.wrap(checkpoint, NodeKind::Error);
             }
         }
 
-        Ok(dict)
+        Ok(())
     }
 
-    // Allow for keyword arguments without parentheses in some contexts
-    fn arguments(&mut self) -> Result<(Vec<Value>, HashMap<String, Value>), ParseError> {
-        let mut args = Vec::new();
-        let mut kwargs = HashMap::new();
-        let mut seen_kwarg = false;
+    /// Parses a block and wraps its statements in a [`NodeKind::Block`] node
+    fn body(&mut self, is_end: impl Fn(&Token) -> bool, expected: &str) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        self.block(is_end, expected)?;
+        self.wrap(checkpoint, NodeKind::Block);
+        Ok(())
+    }
 
-        if matches!(self.peek(), Token::RightParen) {
-            return Ok((args, kwargs));
+    /// Runs `parse`; in recovering mode a failure is recorded, the input is
+    /// rescanned from where `parse` started up to the end of the line, and
+    /// the skipped tokens are kept in an error node
+    fn recoverable(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<(), ParseError>,
+    ) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        let errors = self.errors.len();
+        let start = self.peek_index();
+
+        match parse(self) {
+            Err(err) if self.recovering => {
+                // Errors from nested blocks are found again by the rescan
+                self.tree.truncate(checkpoint);
+                self.errors.truncate(errors);
+                self.errors.push(err);
+                self.pos = start;
+                self.synchronize();
+                self.wrap(checkpoint, NodeKind::Error);
+                Ok(())
+            }
+            result => result,
         }
+    }
 
-        loop {
-            // Check for keyword argument
-            if let Token::Identifier(name) = self.peek() {
-                let saved_pos = self.pos;
-                let name_clone = name.clone();
-                self.advance();
-                if self.match_token(&Token::Colon) {
-                    // It's a keyword argument
-                    seen_kwarg = true;
-                    let value = self.expression()?;
-                    kwargs.insert(name_clone, value);
-                } else {
-                    // It's a positional argument (but only if we haven't seen kwargs yet)
-                    if seen_kwarg {
-                        return Err(ParseError::UnexpectedToken); // Can't have positional after keyword
-                    }
-                    self.pos = saved_pos;
-                    args.push(self.expression()?);
+    /// Skips tokens up to the next statement boundary
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.tokens.get(self.pos) {
+            let newline = *token == Token::Newline;
+            match token {
+                Token::Eof
+                | Token::If
+                | Token::Elif
+                | Token::Else
+                | Token::Endif
+                | Token::Foreach
+                | Token::Endforeach => return,
+                Token::Newline if depth == 0 => {
+                    self.pos += 1;
+                    return;
                 }
-            } else {
-                if seen_kwarg {
-                    return Err(ParseError::UnexpectedToken); // Can't have positional after keyword
+                Token::LeftParen | Token::LeftBracket | Token::LeftBrace => depth += 1,
+                Token::RightParen | Token::RightBracket | Token::RightBrace => {
+                    depth = depth.saturating_sub(1)
                 }
-                args.push(self.expression()?);
+                _ => {}
             }
-
-            if !self.match_token(&Token::Comma) {
-                break;
+            if !newline {
+                self.bump(self.pos);
             }
-
-            // Allow trailing comma
-            if matches!(self.peek(), Token::RightParen) {
-                break;
-            }
-        }
-
-        Ok((args, kwargs))
-    }
-
-    pub fn parse(&mut self) -> Result<Vec<Statement>, ParseError> {
-        let mut statements = Vec::new();
-
-        while !self.is_at_end() {
-            statements.push(self.statement()?);
+            self.pos += 1;
         }
-
-        Ok(statements)
     }
 
-    fn statement(&mut self) -> Result<Statement, ParseError> {
-        match &self.peek() {
-            Token::If => self.if_statement(),
-            Token::Foreach => self.foreach_statement(),
+    fn statement(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        let kind = match &self.peek() {
+            Token::If => {
+                self.if_statement()?;
+                NodeKind::If
+            }
+            Token::Foreach => {
+                self.foreach_statement()?;
+                NodeKind::Foreach
+            }
             Token::Break => {
                 self.advance();
                 self.expect_newline_or_eof()?;
-                Ok(Statement::Break)
+                NodeKind::Break
             }
             Token::Continue => {
                 self.advance();
                 self.expect_newline_or_eof()?;
-                Ok(Statement::Continue)
+                NodeKind::Continue
             }
             _ => {
                 // Try assignment or expression
-                let expr = self.expression()?;
+                self.expression()?;
 
                 // Check for assignment
-                if let Value::Identifier(name) = &expr {
-                    if self.match_token(&Token::Assign) {
-                        let value = self.expression()?;
-                        self.expect_newline_or_eof()?;
-                        return Ok(Statement::Assignment(name.clone(), value));
-                    } else if self.match_token(&Token::AddAssign) {
-                        let value = self.expression()?;
-                        self.expect_newline_or_eof()?;
-                        return Ok(Statement::AddAssignment(name.clone(), value));
-                    }
-                }
+                let kind = if !self.last_node_is(NodeKind::Name) {
+                    NodeKind::ExpressionStatement
+                } else if self.match_token(&Token::Assign) {
+                    self.expression()?;
+                    NodeKind::Assignment
+                } else if self.match_token(&Token::AddAssign) {
+                    self.expression()?;
+                    NodeKind::AddAssignment
+                } else {
+                    NodeKind::ExpressionStatement
+                };
 
                 self.expect_newline_or_eof()?;
-                Ok(Statement::Expression(expr))
+                kind
             }
-        }
+        };
+        self.wrap(checkpoint, kind);
+        Ok(())
     }
 
-    fn if_statement(&mut self) -> Result<Statement, ParseError> {
+    fn if_statement(&mut self) -> Result<(), ParseError> {
+        let is_end = |t: &Token| matches!(t, Token::Elif | Token::Else | Token::Endif);
+
         self.expect(&Token::If)?;
-        let condition = self.expression()?;
-        self.expect_newline()?;
+        self.recoverable(Self::condition)?;
+        self.body(is_end, "'endif'")?;
 
-        let mut then_branch = Vec::new();
-        while !matches!(self.peek(), Token::Elif | Token::Else | Token::Endif) {
-            then_branch.push(self.statement()?);
+        while self.peek() == Token::Elif {
+            let checkpoint = self.tree.len();
+            self.advance();
+            self.recoverable(Self::condition)?;
+            self.body(is_end, "'endif'")?;
+            self.wrap(checkpoint, NodeKind::ElifClause);
         }
 
-        let mut elif_branches = Vec::new();
-        while self.match_token(&Token::Elif) {
-            let elif_condition = self.expression()?;
-            self.expect_newline()?;
-
-            let mut elif_body = Vec::new();
-            while !matches!(self.peek(), Token::Elif | Token::Else | Token::Endif) {
-                elif_body.push(self.statement()?);
-            }
-            elif_branches.push((elif_condition, elif_body));
+        if self.peek() == Token::Else {
+            let checkpoint = self.tree.len();
+            self.advance();
+            self.recoverable(Self::expect_newline)?;
+            self.body(|t| *t == Token::Endif, "'endif'")?;
+            self.wrap(checkpoint, NodeKind::ElseClause);
         }
 
-        let else_branch = if self.match_token(&Token::Else) {
-            self.expect_newline()?;
-            let mut else_body = Vec::new();
-            while !matches!(self.peek(), Token::Endif) {
-                else_body.push(self.statement()?);
-            }
-            Some(else_body)
-        } else {
-            None
-        };
+        self.close_block(&Token::Endif)
+    }
 
-        self.expect(&Token::Endif)?;
-        Ok(Statement::If(
-            condition,
-            then_branch,
-            elif_branches,
-            else_branch,
-        ))
+    fn condition(&mut self) -> Result<(), ParseError> {
+        self.expression()?;
+        self.expect_newline()
     }
 
-    fn foreach_statement(&mut self) -> Result<Statement, ParseError> {
+    fn foreach_statement(&mut self) -> Result<(), ParseError> {
         self.expect(&Token::Foreach)?;
-        let var = if let Token::Identifier(name) = self.advance() {
-            name
-        } else {
-            return Err(ParseError::UnexpectedToken);
-        };
-        self.expect(&Token::Colon)?;
-        let iterable = self.expression()?;
-        self.expect_newline()?;
+        self.recoverable(Self::foreach_header)?;
+        self.body(|t| *t == Token::Endforeach, "'endforeach'")?;
+        self.close_block(&Token::Endforeach)
+    }
 
-        let mut body = Vec::new();
-        while !matches!(self.peek(), Token::Endforeach) {
-            body.push(self.statement()?);
+    /// Expects the keyword ending a block, unless the block was cut off by
+    /// the end of file and the missing keyword already reported
+    fn close_block(&mut self, token: &Token) -> Result<(), ParseError> {
+        if self.recovering && self.peek() == Token::Eof {
+            return Ok(());
         }
+        self.expect(token)
+    }
 
-        self.expect(&Token::Endforeach)?;
-        Ok(Statement::Foreach(var, iterable, body))
+    fn foreach_header(&mut self) -> Result<(), ParseError> {
+        if !matches!(self.advance(), Token::Identifier(_)) {
+            return Err(self.unexpected(self.last, "loop variable name"));
+        }
+        // `foreach key, value : dict`
+        if self.match_token(&Token::Comma) && !matches!(self.advance(), Token::Identifier(_)) {
+            return Err(self.unexpected(self.last, "loop variable name"));
+        }
+        self.expect(&Token::Colon)?;
+        self.expression()?;
+        self.expect_newline()
     }
 
-    fn expression(&mut self) -> Result<Value, ParseError> {
+    fn expression(&mut self) -> Result<(), ParseError> {
         self.ternary()
     }
 
-    fn ternary(&mut self) -> Result<Value, ParseError> {
-        let expr = self.logical_or()?;
+    fn ternary(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        self.logical_or()?;
 
         if self.match_token(&Token::Question) {
-            let true_val = self.expression()?;
+            self.expression()?;
             self.expect(&Token::Colon)?;
-            let false_val = self.expression()?;
-            return Ok(Value::TernaryOp(
-                Box::new(expr),
-                Box::new(true_val),
-                Box::new(false_val),
-            ));
+            self.expression()?;
+            self.wrap(checkpoint, NodeKind::Ternary);
         }
 
-        Ok(expr)
+        Ok(())
     }
 
-    fn logical_or(&mut self) -> Result<Value, ParseError> {
-        let mut left = self.logical_and()?;
+    fn logical_or(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        self.logical_and()?;
 
         while self.match_token(&Token::Or) {
-            let right = self.logical_and()?;
-            left = Value::BinaryOp(Box::new(left), BinaryOperator::Or, Box::new(right));
+            self.logical_and()?;
+            self.wrap(checkpoint, NodeKind::Binary);
         }
 
-        Ok(left)
+        Ok(())
     }
 
-    fn logical_and(&mut self) -> Result<Value, ParseError> {
-        let mut left = self.in_expr()?;
+    fn logical_and(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        self.in_expr()?;
 
         while self.match_token(&Token::And) {
-            let right = self.in_expr()?;
-            left = Value::BinaryOp(Box::new(left), BinaryOperator::And, Box::new(right));
+            self.in_expr()?;
+            self.wrap(checkpoint, NodeKind::Binary);
         }
 
-        Ok(left)
+        Ok(())
     }
 
-    fn in_expr(&mut self) -> Result<Value, ParseError> {
-        let mut left = self.equality()?;
+    fn in_expr(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        self.equality()?;
 
         if self.match_token(&Token::In) {
-            let right = self.equality()?;
-            left = Value::BinaryOp(Box::new(left), BinaryOperator::In, Box::new(right));
-        } else if self.match_token(&Token::Not) && self.match_token(&Token::In) {
-            let right = self.equality()?;
-            left = Value::BinaryOp(Box::new(left), BinaryOperator::NotIn, Box::new(right));
+            self.equality()?;
+            self.wrap(checkpoint, NodeKind::Binary);
+        } else if self.peek() == Token::Not && self.peek_second() == Token::In {
+            self.advance();
+            self.advance();
+            self.equality()?;
+            self.wrap(checkpoint, NodeKind::Binary);
         }
 
-        Ok(left)
+        Ok(())
     }
 
-    fn equality(&mut self) -> Result<Value, ParseError> {
-        let mut left = self.comparison()?;
-
-        while let Some(op) = self.match_tokens(&[Token::Eq, Token::Ne]) {
-            let right = self.comparison()?;
-            let op = match op {
-                Token::Eq => BinaryOperator::Eq,
-                Token::Ne => BinaryOperator::Ne,
-                _ => unreachable!(),
-            };
-            left = Value::BinaryOp(Box::new(left), op, Box::new(right));
-        }
-
-        Ok(left)
+    fn equality(&mut self) -> Result<(), ParseError> {
+        self.binary(&[Token::Eq, Token::Ne], Self::comparison)
     }
 
-    fn comparison(&mut self) -> Result<Value, ParseError> {
-        let mut left = self.addition()?;
-
-        while let Some(op) = self.match_tokens(&[Token::Lt, Token::Le, Token::Gt, Token::Ge]) {
-            let right = self.addition()?;
-            let op = match op {
-                Token::Lt => BinaryOperator::Lt,
-                Token::Le => BinaryOperator::Le,
-                Token::Gt => BinaryOperator::Gt,
-                Token::Ge => BinaryOperator::Ge,
-                _ => unreachable!(),
-            };
-            left = Value::BinaryOp(Box::new(left), op, Box::new(right));
-        }
-
-        Ok(left)
+    fn comparison(&mut self) -> Result<(), ParseError> {
+        self.binary(
+            &[Token::Lt, Token::Le, Token::Gt, Token::Ge],
+            Self::addition,
+        )
     }
 
-    fn addition(&mut self) -> Result<Value, ParseError> {
-        let mut left = self.multiplication()?;
-
-        while let Some(op) = self.match_tokens(&[Token::Plus, Token::Minus]) {
-            let right = self.multiplication()?;
-            let op = match op {
-                Token::Plus => BinaryOperator::Add,
-                Token::Minus => BinaryOperator::Sub,
-                _ => unreachable!(),
-            };
-            left = Value::BinaryOp(Box::new(left), op, Box::new(right));
-        }
-
-        Ok(left)
+    fn addition(&mut self) -> Result<(), ParseError> {
+        self.binary(&[Token::Plus, Token::Minus], Self::multiplication)
     }
 
-    fn multiplication(&mut self) -> Result<Value, ParseError> {
-        let mut left = self.unary()?;
+    fn multiplication(&mut self) -> Result<(), ParseError> {
+        self.binary(&[Token::Star, Token::Slash, Token::Percent], Self::unary)
+    }
 
-        while let Some(op) = self.match_tokens(&[Token::Star, Token::Slash, Token::Percent]) {
-            let right = self.unary()?;
-            let op = match op {
-                Token::Star => BinaryOperator::Mul,
-                Token::Slash => BinaryOperator::Div,
-                Token::Percent => BinaryOperator::Mod,
-                _ => unreachable!(),
-            };
-            left = Value::BinaryOp(Box::new(left), op, Box::new(right));
+    /// Parses a left associative chain of `operand`s separated by `operators`
+    fn binary(
+        &mut self,
+        operators: &[Token],
+        operand: fn(&mut Self) -> Result<(), ParseError>,
+    ) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        operand(self)?;
+
+        while self.match_tokens(operators).is_some() {
+            operand(self)?;
+            self.wrap(checkpoint, NodeKind::Binary);
         }
 
-        Ok(left)
+        Ok(())
     }
 
-    fn unary(&mut self) -> Result<Value, ParseError> {
-        if self.match_token(&Token::Not) {
-            let expr = self.unary()?;
-            return Ok(Value::UnaryOp(UnaryOperator::Not, Box::new(expr)));
-        }
-
-        if self.match_token(&Token::Minus) {
-            let expr = self.unary()?;
-            return Ok(Value::UnaryOp(UnaryOperator::Minus, Box::new(expr)));
+    fn unary(&mut self) -> Result<(), ParseError> {
+        if matches!(self.peek(), Token::Not | Token::Minus) {
+            let checkpoint = self.tree.len();
+            self.advance();
+            self.unary()?;
+            self.wrap(checkpoint, NodeKind::Unary);
+            return Ok(());
         }
 
         self.postfix()
     }
 
-    fn postfix(&mut self) -> Result<Value, ParseError> {
-        let mut expr = self.primary()?;
+    fn postfix(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        self.primary()?;
+        let mut callable = self.last_node_is(NodeKind::Name);
 
         loop {
             match self.peek() {
                 Token::LeftParen => {
-                    // Function or method call
-                    self.advance();
-                    let (args, kwargs) = self.arguments()?;
-                    self.expect(&Token::RightParen)?;
-
-                    if let Value::Identifier(name) = expr {
-                        expr = Value::FunctionCall(name, args, kwargs);
-                    } else if let Value::MethodCall(obj, method, _, _) = expr {
-                        expr = Value::MethodCall(obj, method, args, kwargs);
-                    } else {
-                        return Err(ParseError::UnexpectedToken);
+                    // Function call
+                    if !callable {
+                        return Err(self.error("an operator"));
                     }
+                    self.arguments()?;
+                    self.wrap(checkpoint, NodeKind::FunctionCall);
                 }
                 Token::LeftBracket => {
                     // Subscript
                     self.advance();
-                    let index = self.expression()?;
+                    self.expression()?;
                     self.expect(&Token::RightBracket)?;
-                    expr = Value::Subscript(Box::new(expr), Box::new(index));
+                    self.wrap(checkpoint, NodeKind::Subscript);
                 }
                 Token::Dot => {
                     // Method call
                     self.advance();
-                    if let Token::Identifier(method) = self.advance() {
-                        expr = Value::MethodCall(Box::new(expr), method, vec![], HashMap::new());
-                    } else {
-                        return Err(ParseError::UnexpectedToken);
+                    if !matches!(self.advance(), Token::Identifier(_)) {
+                        return Err(self.unexpected(self.last, "method name"));
+                    }
+                    if self.peek() == Token::LeftParen {
+                        self.arguments()?;
                     }
+                    self.wrap(checkpoint, NodeKind::MethodCall);
                 }
                 _ => break,
             }
+            callable = false;
         }
 
-        Ok(expr)
+        Ok(())
     }
 
-    fn primary(&mut self) -> Result<Value, ParseError> {
-        match self.advance() {
-            Token::String(s) => Ok(Value::String(s)),
-            Token::FormatString(s) => Ok(Value::FormatString(s)),
-            Token::Integer(i) => Ok(Value::Integer(i)),
-            Token::True => Ok(Value::Boolean(true)),
-            Token::False => Ok(Value::Boolean(false)),
-            Token::Identifier(name) => Ok(Value::Identifier(name)),
+    fn primary(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        let kind = match self.advance() {
+            Token::String(_)
+            | Token::FormatString(_)
+            | Token::Integer(_)
+            | Token::True
+            | Token::False => NodeKind::Literal,
+            Token::Identifier(_) => NodeKind::Name,
             Token::LeftParen => {
-                let expr = self.expression()?;
+                self.expression()?;
                 self.expect(&Token::RightParen)?;
-                Ok(expr)
+                NodeKind::Paren
             }
             Token::LeftBracket => {
-                let elements = self.array_elements()?;
+                self.array_elements()?;
                 self.expect(&Token::RightBracket)?;
-                Ok(Value::Array(elements))
+                NodeKind::Array
             }
             Token::LeftBrace => {
-                let dict = self.dict_elements()?;
+                self.dict_elements()?;
                 self.expect(&Token::RightBrace)?;
-                Ok(Value::Dict(dict))
+                NodeKind::Dict
             }
-            _ => Err(ParseError::UnexpectedToken),
-        }
+            _ => return Err(self.unexpected(self.last, "an expression")),
+        };
+        self.wrap(checkpoint, kind);
+        Ok(())
     }
 
-    fn array_elements(&mut self) -> Result<Vec<Value>, ParseError> {
-        let mut elements = Vec::new();
-
+    fn array_elements(&mut self) -> Result<(), ParseError> {
         if matches!(self.peek(), Token::RightBracket) {
-            return Ok(elements);
+            return Ok(());
         }
 
         loop {
-            elements.push(self.expression()?);
+            self.expression()?;
             if !self.match_token(&Token::Comma) {
                 break;
             }
@@ -1069,10 +1184,129 @@ impl Parser {
             }
         }
 
-        Ok(elements)
+        Ok(())
+    }
+
+    fn dict_elements(&mut self) -> Result<(), ParseError> {
+        if matches!(self.peek(), Token::RightBrace) {
+            return Ok(());
+        }
+
+        loop {
+            let checkpoint = self.tree.len();
+
+            // Meson allows both string keys and identifier keys in dict literals
+            match self.peek() {
+                Token::String(_) | Token::Identifier(_) | Token::FormatString(_) => {
+                    self.advance();
+                }
+                _ => return Err(self.error("dictionary key")),
+            }
+
+            self.expect(&Token::Colon)?;
+            self.expression()?;
+            self.wrap(checkpoint, NodeKind::DictEntry);
+
+            if !self.match_token(&Token::Comma) {
+                break;
+            }
+
+            // Allow trailing comma
+            if matches!(self.peek(), Token::RightBrace) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn arguments(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.tree.len();
+        let mut seen_kwarg = false;
+
+        self.expect(&Token::LeftParen)?;
+
+        if !matches!(self.peek(), Token::RightParen) {
+            loop {
+                // Check for keyword argument
+                if matches!(self.peek(), Token::Identifier(_)) && self.peek_second() == Token::Colon
+                {
+                    let kwarg = self.tree.len();
+                    self.advance();
+                    self.advance();
+                    self.expression()?;
+                    self.wrap(kwarg, NodeKind::KeywordArgument);
+                    seen_kwarg = true;
+                } else {
+                    if seen_kwarg {
+                        return Err(self.error("keyword argument")); // Can't have positional after keyword
+                    }
+                    self.expression()?;
+                }
+
+                if !self.match_token(&Token::Comma) {
+                    break;
+                }
+
+                // Allow trailing comma
+                if matches!(self.peek(), Token::RightParen) {
+                    break;
+                }
+            }
+        }
+
+        self.expect(&Token::RightParen)?;
+        self.wrap(checkpoint, NodeKind::ArgumentList);
+        Ok(())
+    }
+
+    // Syntax tree helpers
+
+    /// Appends the token at `index` to the syntax tree
+    fn bump(&mut self, index: usize) {
+        let span = self.spans[index];
+        self.tree.push(SyntaxElement::Token(SyntaxToken {
+            token: self.tokens[index].clone(),
+            text: self.source[span.start..span.end].into(),
+            span,
+            leading_trivia: self.trivia[index].clone(),
+        }));
+    }
+
+    /// Moves everything added to the tree since `checkpoint` into a new node
+    fn wrap(&mut self, checkpoint: usize, kind: NodeKind) {
+        let children = self.tree.split_off(checkpoint);
+        self.tree
+            .push(SyntaxElement::Node(SyntaxNode { kind, children }));
+    }
+
+    fn last_node_is(&self, kind: NodeKind) -> bool {
+        matches!(self.tree.last(), Some(SyntaxElement::Node(node)) if node.kind == kind)
     }
 
     // Helper methods
+    fn peek_index(&self) -> usize {
+        self.tokens[self.pos..]
+            .iter()
+            .position(|t| t != &Token::Newline)
+            .map(|i| self.pos + i)
+            .unwrap_or(self.tokens.len() - 1)
+    }
+
+    fn unexpected(&self, index: usize, expected: &str) -> ParseError {
+        let found = self.tokens.get(index).unwrap_or(&Token::Eof);
+        let span = self.spans.get(index).copied().unwrap_or_default();
+        ParseError::UnexpectedToken {
+            expected: expected.into(),
+            found: found.to_string(),
+            location: Location::new(&self.source, span),
+        }
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        self.unexpected(self.peek_index(), expected)
+    }
+
     fn peek_with_newline(&self) -> Token {
         self.tokens.get(self.pos).cloned().unwrap_or(Token::Eof)
     }
@@ -1085,6 +1319,16 @@ impl Parser {
             .unwrap_or(Token::Eof)
     }
 
+    /// The token after the one returned by [`Parser::peek`]
+    fn peek_second(&self) -> Token {
+        self.tokens[self.pos..]
+            .iter()
+            .filter(|&t| t != &Token::Newline)
+            .nth(1)
+            .cloned()
+            .unwrap_or(Token::Eof)
+    }
+
     fn skip_newline(&mut self) {
         while self.peek_with_newline() == Token::Newline {
             self.pos += 1;
@@ -1092,11 +1336,11 @@ impl Parser {
     }
 
     fn advance(&mut self) -> Token {
-        while self.peek_with_newline() == Token::Newline {
-            self.pos += 1;
-        }
+        self.skip_newline();
         let token = self.peek();
+        self.last = self.pos;
         if !self.is_at_end() {
+            self.bump(self.pos);
             self.pos += 1;
         }
         token
@@ -1125,7 +1369,7 @@ impl Parser {
             self.advance();
             Ok(())
         } else {
-            Err(ParseError::UnexpectedToken)
+            Err(self.error(&token.to_string()))
         }
     }
 
@@ -1134,7 +1378,7 @@ impl Parser {
             self.skip_newline();
             Ok(())
         } else {
-            Err(ParseError::UnexpectedToken)
+            Err(self.error("newline"))
         }
     }
 
@@ -1143,7 +1387,7 @@ impl Parser {
             self.skip_newline();
             Ok(())
         } else {
-            Err(ParseError::UnexpectedToken)
+            Err(self.error("newline"))
         }
     }
 
@@ -1153,7 +1397,7 @@ impl Parser {
 }
 
 // Example usage
-pub fn parse_meson_file(content: &str) -> Result<Vec<Statement>, ParseError> {
+pub fn parse_meson_file(content: &str) -> Result<Vec<Spanned<Statement>>, ParseError> {
     let mut parser = Parser::new(content);
     parser.parse()
 }
@@ -1198,4 +1442,194 @@ Unsupported architecture: "@0@"
         let result = parse_meson_file(input);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_unexpected_token_location() {
+        let input = "project('foo')\nx = files('a.c' 'b.c')\n";
+        let Err(ParseError::UnexpectedToken {
+            expected,
+            found,
+            location,
+        }) = parse_meson_file(input)
+        else {
+            panic!("Expected an unexpected token error");
+        };
+        assert_eq!(expected, "')'");
+        assert_eq!(found, "string \"b.c\"");
+        assert_eq!((location.line, location.column), (2, 17));
+        assert_eq!(location.source_line, "x = files('a.c' 'b.c')");
+        assert_eq!(
+            location.snippet(),
+            " 2 | x = files('a.c' 'b.c')\n   |                 ^"
+        );
+    }
+
+    #[test]
+    fn test_statement_spans() {
+        let input = "a = 1\n\nfoo(a,\n    2)\n";
+        let statements = parse_meson_file(input).unwrap();
+        let spans = statements.iter().map(|s| s.span).collect::<Vec<_>>();
+        assert_eq!(spans, vec![Span::new(0, 5), Span::new(7, 20)]);
+    }
+
+    #[test]
+    fn test_recovering_reports_all_errors() {
+        let input = r#"
+a = foo(1 2)
+b = 2
+if b == 2 3
+  c = [1,
+       2 3]
+  d = 4
+endif
+foreach x in y
+  e = )
+endforeach
+f = 5
+"#;
+        let (statements, errors) = Parser::new(input).parse_recovering();
+
+        let lines = errors
+            .iter()
+            .map(|e| e.location().unwrap().line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![2, 4, 6, 9, 10]);
+
+        let names = statements
+            .iter()
+            .filter_map(|s| match &s.node {
+                Statement::Assignment(name, _) => Some(name.as_str()),
+                Statement::If(..) => Some("if"),
+                Statement::Foreach(..) => Some("foreach"),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["b", "if", "foreach", "f"]);
+
+        let Statement::If(_, then_branch, _, _) = &statements[1].node else {
+            panic!("Expected an if statement");
+        };
+        assert_eq!(then_branch.len(), 1);
+    }
+
+    #[test]
+    fn test_recovering_unterminated_blocks() {
+        let messages = |input: &str| {
+            let (statements, errors) = Parser::new(input).parse_recovering();
+            let errors = errors
+                .iter()
+                .map(|e| {
+                    let ParseError::UnexpectedToken {
+                        expected,
+                        found,
+                        location,
+                    } = e
+                    else {
+                        panic!("Unexpected error {e}");
+                    };
+                    format!(
+                        "{}:{}: expected {expected}, found {found}",
+                        location.line, location.column
+                    )
+                })
+                .collect::<Vec<_>>();
+            (statements, errors)
+        };
+
+        let (statements, errors) = messages("foreach x : y\n  a = 1\n");
+        assert_eq!(
+            errors,
+            vec!["3:1: expected 'endforeach', found end of file"]
+        );
+        let [statement] = &statements[..] else {
+            panic!("Expected a single statement, got {statements:?}");
+        };
+        let Statement::Foreach(_, _, body) = &statement.node else {
+            panic!("Expected a foreach statement");
+        };
+        assert_eq!(body.len(), 1);
+
+        let (statements, errors) = messages("a = (\nif x\n  b = 1\nelse\n  c = 2\n");
+        assert_eq!(
+            errors,
+            vec![
+                "2:1: expected an expression, found 'if'",
+                "6:1: expected 'endif', found end of file",
+            ]
+        );
+        let [statement] = &statements[..] else {
+            panic!("Expected a single statement, got {statements:?}");
+        };
+        let Statement::If(_, then_branch, _, else_branch) = &statement.node else {
+            panic!("Expected an if statement");
+        };
+        assert_eq!(then_branch.len(), 1);
+        assert_eq!(else_branch.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_cst_round_trip() {
+        let input = concat!(
+            "# leading comment\r\n",
+            "project('foo', \"c\",  version : '1.0')  # trailing\n",
+            "\n",
+            "x = f'@a@' + r'raw\\n' + '''multi \\\n",
+            "    line'''\n",
+            "if x.startswith('a') and not (1 in [1, 2,])\n",
+            "\tmessage({'k' : -0x1F}) # comment\n",
+            "elif x not in ['b']\n",
+            "else\n",
+            "endif\n",
+            "foreach y : [] \n  continue\nendforeach\n",
+            "# no trailing newline",
+        );
+        let cst = Parser::new(input).parse_cst().unwrap();
+        assert_eq!(cst.to_string(), input);
+
+        let comments = cst
+            .first_token()
+            .unwrap()
+            .leading_trivia
+            .iter()
+            .filter(|t| t.kind == cst::TriviaKind::Comment)
+            .map(|t| t.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(comments, vec!["# leading comment"]);
+        assert_eq!(Parser::new(input).parse().unwrap(), lower::lower(&cst));
+    }
+
+    #[test]
+    fn test_cst_round_trip_with_errors() {
+        let input = "a = foo(1 2)\nif b == 2 3\n  c = )\nendif\nendforeach\nd = 1\n";
+        let (cst, errors) = Parser::new(input).parse_cst_recovering();
+        assert_eq!(errors.len(), 4);
+        assert_eq!(cst.to_string(), input);
+    }
+
+    #[test]
+    fn test_foreach_two_variables() {
+        let input = "foreach k, v : {'a' : 1}\nendforeach\n";
+        let statements = parse_meson_file(input).unwrap();
+        let Statement::Foreach(vars, Value::Dict(_), body) = &statements[0].node else {
+            panic!("Expected a foreach over a dict");
+        };
+        assert_eq!(vars, &vec!["k".to_string(), "v".to_string()]);
+        assert!(body.is_empty());
+
+        let Err(err) = parse_meson_file("foreach k, : d\nendforeach\n") else {
+            panic!("Expected a missing loop variable error");
+        };
+        assert_eq!(err.location().unwrap().column, 12);
+    }
+
+    #[test]
+    fn test_dict_keeps_insertion_order() {
+        let input = "d = {'b' : 1, 'a' : 2, 'c' : 3}\n";
+        let statements = parse_meson_file(input).unwrap();
+        let Statement::Assignment(_, Value::Dict(dict)) = &statements[0].node else {
+            panic!("Expected a dict assignment");
+        };
+        let keys = dict.keys().map(String::as_str).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["b", "a", "c"]);
+    }
 }
@@ -0,0 +1,178 @@
+//! Lossless concrete syntax tree
+//!
+//! Unlike [`Statement`](super::Statement) and [`Value`](super::Value), the
+//! concrete syntax tree keeps every byte of the input: tokens keep their
+//! original text (including string quoting and escapes), and whitespace,
+//! newlines and comments are attached to the token that follows them as
+//! leading trivia. Printing the tree with [`Display`](core::fmt::Display)
+//! reproduces the source exactly.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{Span, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Root,
+    Block,
+
+    // Statements
+    Assignment,
+    AddAssignment,
+    ExpressionStatement,
+    If,
+    ElifClause,
+    ElseClause,
+    Foreach,
+    Break,
+    Continue,
+
+    // Expressions
+    Literal,
+    Name,
+    Paren,
+    Array,
+    Dict,
+    DictEntry,
+    FunctionCall,
+    MethodCall,
+    ArgumentList,
+    KeywordArgument,
+    Binary,
+    Unary,
+    Subscript,
+    Ternary,
+
+    /// Tokens skipped while recovering from a syntax error
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    Comment,
+    /// Characters the lexer does not recognise
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub token: Token,
+    /// The token exactly as written in the source
+    pub text: String,
+    pub span: Span,
+    /// Whitespace, newlines and comments between the previous token and this one
+    pub leading_trivia: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    /// Direct child nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Direct child tokens
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    pub fn first_token(&self) -> Option<&SyntaxToken> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(node) => node.first_token(),
+        })
+    }
+
+    pub fn last_token(&self) -> Option<&SyntaxToken> {
+        self.children.iter().rev().find_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(node) => node.last_token(),
+        })
+    }
+
+    /// The span from the first to the last token, excluding leading trivia
+    pub fn span(&self) -> Span {
+        match (self.first_token(), self.last_token()) {
+            (Some(first), Some(last)) => first.span.to(last.span),
+            _ => Span::default(),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading_trivia {
+            f.write_str(&trivia.text)?;
+        }
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{node}")?,
+                SyntaxElement::Token(token) => write!(f, "{token}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits the text between two tokens into trivia
+pub(super) fn lex_trivia(text: &str) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+    let mut rest = text;
+
+    while let Some(ch) = rest.chars().next() {
+        let (kind, len) = match ch {
+            ' ' | '\t' | '\r' => {
+                let len = rest
+                    .find(|c| !matches!(c, ' ' | '\t' | '\r'))
+                    .unwrap_or(rest.len());
+                (TriviaKind::Whitespace, len)
+            }
+            '\n' => (TriviaKind::Newline, 1),
+            '#' => (
+                TriviaKind::Comment,
+                rest.find(['\r', '\n']).unwrap_or(rest.len()),
+            ),
+            ch => (TriviaKind::Skipped, ch.len_utf8()),
+        };
+        trivia.push(Trivia {
+            kind,
+            text: rest[..len].into(),
+        });
+        rest = &rest[len..];
+    }
+
+    trivia
+}
@@ -1,14 +1,57 @@
+use alloc::string::String;
 use core::fmt;
 
-#[derive(Debug)]
+use super::span::Location;
+use crate::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParseError {
-    UnexpectedToken,
+    UnexpectedToken {
+        expected: String,
+        found: String,
+        location: Location,
+    },
+    InvalidExpression(String),
+}
+
+impl ParseError {
+    /// Attaches the path of the file being parsed to the error location
+    pub fn with_file(self, file: Path) -> Self {
+        match self {
+            ParseError::UnexpectedToken {
+                expected,
+                found,
+                location,
+            } => ParseError::UnexpectedToken {
+                expected,
+                found,
+                location: location.with_file(file),
+            },
+            err => err,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            ParseError::UnexpectedToken { location, .. } => Some(location),
+            ParseError::InvalidExpression(_) => None,
+        }
+    }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ParseError::UnexpectedToken => write!(f, "Unexpected token"),
+            ParseError::UnexpectedToken {
+                expected,
+                found,
+                location,
+            } => write!(
+                f,
+                "{location}: expected {expected}, found {found}\n{}",
+                location.snippet()
+            ),
+            ParseError::InvalidExpression(msg) => write!(f, "Invalid expression: {msg}"),
         }
     }
 }
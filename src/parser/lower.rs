@@ -0,0 +1,200 @@
+//! Lowering of the concrete syntax tree to the [`Statement`]/[`Value`] AST
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use super::cst::{NodeKind, SyntaxNode, SyntaxToken};
+use super::{BinaryOperator, Spanned, Statement, Token, UnaryOperator, Value};
+
+/// Lowers the statements of a root or block node. Error nodes left behind
+/// by a recovering parse are dropped.
+pub(super) fn lower(node: &SyntaxNode) -> Vec<Spanned<Statement>> {
+    node.nodes().filter_map(statement).collect()
+}
+
+fn statement(node: &SyntaxNode) -> Option<Spanned<Statement>> {
+    let statement = match node.kind {
+        NodeKind::Assignment => Statement::Assignment(name(child(node, 0)), expr(child(node, 1))),
+        NodeKind::AddAssignment => {
+            Statement::AddAssignment(name(child(node, 0)), expr(child(node, 1)))
+        }
+        NodeKind::ExpressionStatement => Statement::Expression(expr(child(node, 0))),
+        NodeKind::If => {
+            let mut nodes = node.nodes();
+            let if_condition = condition(nodes.next());
+            let then_branch = lower(nodes.next().expect("if without a body"));
+
+            let mut elif_branches = Vec::new();
+            let mut else_branch = None;
+            for clause in nodes {
+                match clause.kind {
+                    NodeKind::ElifClause => {
+                        let mut nodes = clause.nodes();
+                        let elif_condition = condition(nodes.next());
+                        let body = lower(nodes.next().expect("elif without a body"));
+                        elif_branches.push((elif_condition, body));
+                    }
+                    NodeKind::ElseClause => {
+                        let body = clause.nodes().find(|n| n.kind == NodeKind::Block);
+                        else_branch = Some(lower(body.expect("else without a body")));
+                    }
+                    _ => {}
+                }
+            }
+
+            Statement::If(if_condition, then_branch, elif_branches, else_branch)
+        }
+        NodeKind::Foreach => {
+            let vars = node
+                .tokens()
+                .filter_map(|t| match &t.token {
+                    Token::Identifier(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            let mut nodes = node.nodes();
+            let iterable = match nodes.next() {
+                Some(n) if n.kind != NodeKind::Error => expr(n),
+                _ => Value::Array(vec![]),
+            };
+            let body = lower(nodes.next().expect("foreach without a body"));
+            Statement::Foreach(vars, iterable, body)
+        }
+        NodeKind::Break => Statement::Break,
+        NodeKind::Continue => Statement::Continue,
+        _ => return None,
+    };
+    Some(Spanned::new(statement, node.span()))
+}
+
+/// Lowers an `if`/`elif` condition, which is an error node if it failed to parse
+fn condition(node: Option<&SyntaxNode>) -> Value {
+    match node {
+        Some(node) if node.kind != NodeKind::Error => expr(node),
+        _ => Value::Boolean(false),
+    }
+}
+
+fn expr(node: &SyntaxNode) -> Value {
+    match node.kind {
+        NodeKind::Literal => match &first_token(node).token {
+            Token::String(s) => Value::String(s.clone()),
+            Token::FormatString(s) => Value::FormatString(s.clone()),
+            Token::Integer(i) => Value::Integer(*i),
+            Token::True => Value::Boolean(true),
+            _ => Value::Boolean(false),
+        },
+        NodeKind::Name => Value::Identifier(name(node)),
+        NodeKind::Paren => expr(child(node, 0)),
+        NodeKind::Array => Value::Array(node.nodes().map(expr).collect()),
+        NodeKind::Dict => Value::Dict(
+            node.nodes()
+                .map(|entry| (key(first_token(entry)), expr(child(entry, 0))))
+                .collect(),
+        ),
+        NodeKind::FunctionCall => {
+            let (args, kwargs) = arguments(node.nodes().nth(1));
+            Value::FunctionCall(name(child(node, 0)), args, kwargs, node.span())
+        }
+        NodeKind::MethodCall => {
+            let method = node
+                .tokens()
+                .find(|t| matches!(t.token, Token::Identifier(_)))
+                .expect("method call without a name");
+            let (args, kwargs) = arguments(node.nodes().nth(1));
+            Value::MethodCall(
+                Box::new(expr(child(node, 0))),
+                key(method),
+                args,
+                kwargs,
+                method.span.to(node.span()),
+            )
+        }
+        NodeKind::Binary => {
+            let mut ops = node.tokens().map(|t| &t.token);
+            let op = match (ops.next(), ops.next()) {
+                (Some(Token::Not), Some(Token::In)) => BinaryOperator::NotIn,
+                (Some(Token::In), _) => BinaryOperator::In,
+                (Some(Token::Or), _) => BinaryOperator::Or,
+                (Some(Token::And), _) => BinaryOperator::And,
+                (Some(Token::Eq), _) => BinaryOperator::Eq,
+                (Some(Token::Ne), _) => BinaryOperator::Ne,
+                (Some(Token::Lt), _) => BinaryOperator::Lt,
+                (Some(Token::Le), _) => BinaryOperator::Le,
+                (Some(Token::Gt), _) => BinaryOperator::Gt,
+                (Some(Token::Ge), _) => BinaryOperator::Ge,
+                (Some(Token::Plus), _) => BinaryOperator::Add,
+                (Some(Token::Minus), _) => BinaryOperator::Sub,
+                (Some(Token::Star), _) => BinaryOperator::Mul,
+                (Some(Token::Slash), _) => BinaryOperator::Div,
+                (Some(Token::Percent), _) => BinaryOperator::Mod,
+                (op, _) => unreachable!("invalid binary operator {op:?}"),
+            };
+            Value::BinaryOp(
+                Box::new(expr(child(node, 0))),
+                op,
+                Box::new(expr(child(node, 1))),
+            )
+        }
+        NodeKind::Unary => {
+            let op = match first_token(node).token {
+                Token::Not => UnaryOperator::Not,
+                _ => UnaryOperator::Minus,
+            };
+            Value::UnaryOp(op, Box::new(expr(child(node, 0))))
+        }
+        NodeKind::Subscript => Value::Subscript(
+            Box::new(expr(child(node, 0))),
+            Box::new(expr(child(node, 1))),
+        ),
+        NodeKind::Ternary => Value::TernaryOp(
+            Box::new(expr(child(node, 0))),
+            Box::new(expr(child(node, 1))),
+            Box::new(expr(child(node, 2))),
+        ),
+        kind => unreachable!("{kind:?} is not an expression"),
+    }
+}
+
+fn arguments(node: Option<&SyntaxNode>) -> (Vec<Value>, HashMap<String, Value>) {
+    let mut args = Vec::new();
+    let mut kwargs = HashMap::new();
+
+    for arg in node.into_iter().flat_map(|n| n.nodes()) {
+        if arg.kind == NodeKind::KeywordArgument {
+            kwargs.insert(key(first_token(arg)), expr(child(arg, 0)));
+        } else {
+            args.push(expr(arg));
+        }
+    }
+
+    (args, kwargs)
+}
+
+fn child(node: &SyntaxNode, n: usize) -> &SyntaxNode {
+    node.nodes()
+        .nth(n)
+        .unwrap_or_else(|| unreachable!("{:?} is missing child {n}", node.kind))
+}
+
+fn first_token(node: &SyntaxNode) -> &SyntaxToken {
+    node.first_token()
+        .unwrap_or_else(|| unreachable!("{:?} has no tokens", node.kind))
+}
+
+/// The identifier of a name node
+fn name(node: &SyntaxNode) -> String {
+    key(first_token(node))
+}
+
+/// The text of an identifier, or the value of a string used as a dict key
+fn key(token: &SyntaxToken) -> String {
+    match &token.token {
+        Token::Identifier(s) | Token::String(s) | Token::FormatString(s) => s.clone(),
+        token => unreachable!("{token:?} is not a name"),
+    }
+}
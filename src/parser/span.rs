@@ -0,0 +1,98 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+
+use crate::path::Path;
+
+/// A byte range into the source text a node or token was parsed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns a span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// A node of the syntax tree together with the span it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+}
+
+/// A span resolved to a human readable position in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The file the source was read from, if any
+    pub file: Option<Path>,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number, counted in characters
+    pub column: usize,
+    /// The full text of the line the span starts on
+    pub source_line: String,
+}
+
+impl Location {
+    pub fn new(source: &str, span: Span) -> Self {
+        let offset = span.start.min(source.len());
+        let line_start = source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[offset..]
+            .find('\n')
+            .map(|i| offset + i)
+            .unwrap_or(source.len());
+
+        Self {
+            file: None,
+            line: source[..line_start].matches('\n').count() + 1,
+            column: source[line_start..offset].chars().count() + 1,
+            source_line: source[line_start..line_end].trim_end_matches('\r').into(),
+        }
+    }
+
+    pub fn with_file(mut self, file: Path) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// Renders the offending source line with a caret under the column
+    pub fn snippet(&self) -> String {
+        let gutter = format!("{}", self.line);
+        let pad = " ".repeat(gutter.len());
+        // Keep tabs so the caret lines up with the source line
+        let indent: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!(" {gutter} | {}\n {pad} | {indent}^", self.source_line)
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{file}:{}:{}", self.line, self.column),
+            None => write!(f, "<string>:{}:{}", self.line, self.column),
+        }
+    }
+}
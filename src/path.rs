@@ -72,6 +72,16 @@ impl Path {
         Self(new_path)
     }
 
+    /// Returns this path relative to `base`, or `None` if it is not inside `base`
+    pub fn strip_prefix(&self, base: &Path) -> Option<Self> {
+        let base = base.0.trim_end_matches(SEP);
+        if base.is_empty() {
+            return Some(self.clone());
+        }
+        let rest = self.0.strip_prefix(base)?;
+        rest.strip_prefix(SEP).map(Self::from)
+    }
+
     pub fn filename(&self) -> &str {
         self.0.rsplit(SEP).next().unwrap_or(&self.0) // Fallback to the whole path if no separator is found
     }
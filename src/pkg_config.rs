@@ -0,0 +1,548 @@
+//! A pkg-config implementation over the runtime's filesystem
+//!
+//! Embeddings that cannot spawn `pkg-config` may still have the `.pc` files
+//! in a sysroot, so they are found, parsed and resolved here into the flags
+//! `pkg-config --cflags --libs` would print.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+
+use anyhow::{Result, bail};
+use hashbrown::HashMap;
+
+use crate::path::Path;
+
+/// Include and library directories the compiler searches anyway, which are
+/// left out of the flags
+const SYSTEM_INCLUDE_DIRS: &[&str] = &["/usr/include"];
+const SYSTEM_LIBRARY_DIRS: &[&str] = &["/usr/lib", "/usr/lib64", "/lib", "/lib64"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn parse(op: &str) -> Option<Self> {
+        Some(match op {
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessEqual,
+            "=" | "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            ">=" => Comparison::GreaterEqual,
+            ">" => Comparison::Greater,
+            _ => return None,
+        })
+    }
+
+    pub fn matches(self, version: &str, wanted: &str) -> bool {
+        let ordering = compare_versions(version, wanted);
+        match self {
+            Comparison::Less => ordering.is_lt(),
+            Comparison::LessEqual => ordering.is_le(),
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::NotEqual => ordering.is_ne(),
+            Comparison::GreaterEqual => ordering.is_ge(),
+            Comparison::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Less => "<",
+            Comparison::LessEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::GreaterEqual => ">=",
+            Comparison::Greater => ">",
+        })
+    }
+}
+
+/// A package named in `Requires:`, optionally with a version constraint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    pub name: String,
+    pub version: Option<(Comparison, String)>,
+}
+
+/// The contents of a `.pc` file, with its variables expanded
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PcFile {
+    pub version: String,
+    pub variables: HashMap<String, String>,
+    pub requires: Vec<Requirement>,
+    pub requires_private: Vec<Requirement>,
+    pub cflags: Vec<String>,
+    pub libs: Vec<String>,
+    pub libs_private: Vec<String>,
+}
+
+impl PcFile {
+    /// Parses a `.pc` file found in `dir`, which is the value of the
+    /// predefined `pcfiledir` variable
+    pub fn parse(text: &str, dir: &str, sysroot: &str) -> Result<PcFile> {
+        let mut pc = PcFile::default();
+        pc.variables.insert("pcfiledir".into(), dir.into());
+        pc.variables.insert("pc_sysrootdir".into(), sysroot.into());
+
+        for line in logical_lines(text) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let key_len = line
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')))
+                .unwrap_or(line.len());
+            let (key, rest) = line.split_at(key_len);
+            let rest = rest.trim_start();
+
+            if let Some(value) = rest.strip_prefix('=') {
+                let value = expand(value.trim(), &pc.variables)?;
+                pc.variables.insert(key.into(), value);
+                continue;
+            }
+            let Some(value) = rest.strip_prefix(':') else {
+                continue;
+            };
+            let value = expand(value.trim(), &pc.variables)?;
+
+            match key {
+                "Version" => pc.version = value,
+                "Requires" => pc.requires = parse_requires(&value)?,
+                "Requires.private" => pc.requires_private = parse_requires(&value)?,
+                "Cflags" | "CFlags" => pc.cflags = split_args(&value)?,
+                "Libs" => pc.libs = split_args(&value)?,
+                "Libs.private" => pc.libs_private = split_args(&value)?,
+                _ => {}
+            }
+        }
+
+        Ok(pc)
+    }
+}
+
+/// Joins lines ending in a backslash and drops comments
+fn logical_lines(text: &str) -> impl Iterator<Item = String> + '_ {
+    let mut lines = text.lines();
+    core::iter::from_fn(move || {
+        let mut logical = String::new();
+        loop {
+            let line = lines.next()?;
+            match line.strip_suffix('\\') {
+                Some(line) => logical.push_str(line),
+                None => {
+                    logical.push_str(line);
+                    break;
+                }
+            }
+        }
+
+        let mut escaped = false;
+        let comment = logical.char_indices().find_map(|(i, c)| {
+            let start = c == '#' && !escaped;
+            escaped = c == '\\' && !escaped;
+            start.then_some(i)
+        });
+        if let Some(i) = comment {
+            logical.truncate(i);
+        }
+        Some(logical.replace("\\#", "#"))
+    })
+}
+
+/// Expands `${name}` references to variables defined earlier, and `$$`
+fn expand(value: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let Some(end) = after.find('}') else {
+                bail!("Unterminated variable reference in '{value}'");
+            };
+            let name = &after[..end];
+            let Some(variable) = variables.get(name) else {
+                bail!("Variable '{name}' not defined");
+            };
+            out.push_str(variable);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Parses a list like `glib-2.0 >= 2.50, zlib`
+fn parse_requires(value: &str) -> Result<Vec<Requirement>> {
+    let is_op = |c: char| matches!(c, '<' | '>' | '=' | '!');
+
+    let mut tokens = Vec::new();
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if rest.is_empty() {
+            break;
+        }
+        let len = match rest.starts_with(is_op) {
+            true => rest.find(|c| !is_op(c)),
+            false => rest.find(|c: char| c == ',' || c.is_whitespace() || is_op(c)),
+        };
+        let (token, after) = rest.split_at(len.unwrap_or(rest.len()));
+        tokens.push(token);
+        rest = after;
+    }
+
+    let mut requires = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(name) = tokens.next() {
+        let comparison = tokens.peek().and_then(|op| Comparison::parse(op));
+        let version = match comparison {
+            Some(comparison) => {
+                tokens.next();
+                let Some(version) = tokens.next() else {
+                    bail!("Expected a version after '{name} {comparison}'");
+                };
+                Some((comparison, version.into()))
+            }
+            None => None,
+        };
+        requires.push(Requirement {
+            name: name.into(),
+            version,
+        });
+    }
+    Ok(requires)
+}
+
+/// Splits flags the way a shell would, honouring quotes and backslashes
+fn split_args(value: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(arg.take()),
+            '\'' => {
+                let arg = arg.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => bail!("Unterminated quote in '{value}'"),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => arg.extend(chars.next()),
+                        Some(c) => arg.push(c),
+                        None => bail!("Unterminated quote in '{value}'"),
+                    }
+                }
+            }
+            '\\' => arg.get_or_insert_default().extend(chars.next()),
+            c => arg.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+/// Compares versions like pkg-config does, segment by segment, where
+/// numeric segments compare as numbers and are newer than alphabetic ones
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        a = trim_separators(a);
+        b = trim_separators(b);
+        if a.is_empty() || b.is_empty() {
+            break;
+        }
+
+        let numeric = a[0].is_ascii_digit();
+        let segment = |s: &[u8]| match numeric {
+            true => s.iter().take_while(|c| c.is_ascii_digit()).count(),
+            false => s.iter().take_while(|c| c.is_ascii_alphabetic()).count(),
+        };
+        let (len_a, len_b) = (segment(a), segment(b));
+        if len_b == 0 {
+            return match numeric {
+                true => Ordering::Greater,
+                false => Ordering::Less,
+            };
+        }
+
+        let (seg_a, seg_b) = (&a[..len_a], &b[..len_b]);
+        let ordering = match numeric {
+            true => {
+                let seg_a = trim_zeros(seg_a);
+                let seg_b = trim_zeros(seg_b);
+                seg_a.len().cmp(&seg_b.len()).then(seg_a.cmp(seg_b))
+            }
+            false => seg_a.cmp(seg_b),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+        a = &a[len_a..];
+        b = &b[len_b..];
+    }
+
+    // Whichever version has segments left is newer
+    a.len().cmp(&b.len())
+}
+
+fn trim_separators(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(u8::is_ascii_alphanumeric);
+    &s[start.unwrap_or(s.len())..]
+}
+
+fn trim_zeros(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|&c| c != b'0');
+    &s[start.unwrap_or(s.len())..]
+}
+
+/// A package resolved with everything it requires
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub version: String,
+    pub variables: HashMap<String, String>,
+    pub cflags: Vec<String>,
+    pub libs: Vec<String>,
+}
+
+/// Looks up `.pc` files in a list of directories
+pub struct PkgConfig<'a> {
+    pub dirs: Vec<Path>,
+    /// Prepended to include and library directories, as with
+    /// `PKG_CONFIG_SYSROOT_DIR`
+    pub sysroot: Option<String>,
+    pub read_file: &'a dyn Fn(&Path) -> Option<Vec<u8>>,
+}
+
+impl PkgConfig<'_> {
+    /// Finds and parses the `.pc` file of a package from the first directory
+    /// that has one
+    pub fn load(&self, name: &str) -> Result<Option<PcFile>> {
+        for dir in &self.dirs {
+            let Some(data) = (self.read_file)(&dir.join(format!("{name}.pc"))) else {
+                continue;
+            };
+            let text = String::from_utf8_lossy(&data);
+            let sysroot = self.sysroot.as_deref().unwrap_or_default();
+            return match PcFile::parse(&text, dir.as_ref(), sysroot) {
+                Ok(pc) => Ok(Some(pc)),
+                Err(err) => bail!("Failed to parse {dir}/{name}.pc: {err}"),
+            };
+        }
+        Ok(None)
+    }
+
+    /// Resolves a package, or returns `None` if there is no `.pc` file for
+    /// it. The private libraries are only included when linking statically,
+    /// but the flags of private requirements are always included.
+    pub fn resolve(&self, name: &str, static_libs: bool) -> Result<Option<Package>> {
+        let Some(pc) = self.load(name)? else {
+            return Ok(None);
+        };
+
+        let mut cflags = Vec::new();
+        let mut libs = Vec::new();
+        let mut stack = Vec::from([String::from(name)]);
+        self.collect(&pc, static_libs, true, &mut stack, &mut cflags, &mut libs)?;
+
+        Ok(Some(Package {
+            version: pc.version,
+            variables: pc.variables,
+            cflags: self.finish(cflags, "-I", SYSTEM_INCLUDE_DIRS),
+            libs: self.finish(libs, "-L", SYSTEM_LIBRARY_DIRS),
+        }))
+    }
+
+    fn collect(
+        &self,
+        pc: &PcFile,
+        static_libs: bool,
+        with_libs: bool,
+        stack: &mut Vec<String>,
+        cflags: &mut Vec<String>,
+        libs: &mut Vec<String>,
+    ) -> Result<()> {
+        cflags.extend(pc.cflags.iter().cloned());
+        if with_libs {
+            libs.extend(pc.libs.iter().cloned());
+            if static_libs {
+                libs.extend(pc.libs_private.iter().cloned());
+            }
+        }
+
+        let public = pc.requires.iter().map(|req| (req, with_libs));
+        let private = pc.requires_private.iter();
+        let private = private.map(|req| (req, with_libs && static_libs));
+        for (req, with_libs) in public.chain(private) {
+            if stack.contains(&req.name) {
+                continue;
+            }
+            let parent = stack.last().cloned().unwrap_or_default();
+            let Some(dep) = self.load(&req.name)? else {
+                bail!("Package '{}', required by '{parent}', not found", req.name);
+            };
+            if let Some((comparison, wanted)) = &req.version
+                && !comparison.matches(&dep.version, wanted)
+            {
+                bail!(
+                    "Package '{}' has version '{}', but '{parent}' requires {comparison} {wanted}",
+                    req.name,
+                    dep.version
+                );
+            }
+
+            stack.push(req.name.clone());
+            self.collect(&dep, static_libs, with_libs, stack, cflags, libs)?;
+            stack.pop();
+        }
+        Ok(())
+    }
+
+    /// Removes duplicate flags and the system directories, and prepends the
+    /// sysroot to the remaining directories. Directories keep their first
+    /// occurrence, everything else its last to preserve the link order.
+    fn finish(&self, flags: Vec<String>, dir_flag: &str, system_dirs: &[&str]) -> Vec<String> {
+        let mut out = Vec::<String>::new();
+        for (i, flag) in flags.iter().enumerate() {
+            let Some(dir) = flag.strip_prefix(dir_flag) else {
+                if !flags[i + 1..].contains(flag) {
+                    out.push(flag.clone());
+                }
+                continue;
+            };
+            if system_dirs.contains(&dir.trim_end_matches('/')) || flags[..i].contains(flag) {
+                continue;
+            }
+            match &self.sysroot {
+                Some(sysroot) if dir.starts_with('/') => {
+                    out.push(format!("{dir_flag}{}{dir}", sysroot.trim_end_matches('/')));
+                }
+                _ => out.push(flag.clone()),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.2.10", "1.2.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.02", "1.2"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.2a", "1.2b"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.1", "1.2rc1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_parse() {
+        let pc = PcFile::parse(
+            r#"
+prefix=/usr # comment
+libdir=${prefix}/lib
+name = foo
+
+Version: 1.2.3
+Requires: bar >= 2.0, baz,qux<3
+Cflags: -I${prefix}/include/${name} -DPATH="a b" \
+    -DX=1
+Libs: -L${libdir} -lfoo
+"#,
+            "/usr/lib/pkgconfig",
+            "",
+        )
+        .unwrap();
+
+        assert_eq!(pc.version, "1.2.3");
+        assert_eq!(pc.variables["libdir"], "/usr/lib");
+        assert_eq!(pc.variables["pcfiledir"], "/usr/lib/pkgconfig");
+        assert_eq!(
+            pc.requires,
+            vec![
+                Requirement {
+                    name: "bar".into(),
+                    version: Some((Comparison::GreaterEqual, "2.0".into())),
+                },
+                Requirement {
+                    name: "baz".into(),
+                    version: None,
+                },
+                Requirement {
+                    name: "qux".into(),
+                    version: Some((Comparison::Less, "3".into())),
+                },
+            ]
+        );
+        assert_eq!(pc.cflags, vec!["-I/usr/include/foo", "-DPATH=a b", "-DX=1"]);
+        assert_eq!(pc.libs, vec!["-L/usr/lib", "-lfoo"]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let files: HashMap<_, _> = HashMap::from_iter([
+            (
+                "/sysroot/lib/pkgconfig/foo.pc",
+                "prefix=/opt\nVersion: 1.0\nRequires: bar >= 2\nRequires.private: baz\nCflags: -I${prefix}/include\nLibs: -L/usr/lib -L${prefix}/lib -lfoo\nLibs.private: -lm\n",
+            ),
+            (
+                "/sysroot/lib/pkgconfig/bar.pc",
+                "Version: 2.1\nCflags: -I/usr/include -DBAR\nLibs: -L/opt/lib -lbar -lfoo\n",
+            ),
+            (
+                "/sysroot/lib/pkgconfig/baz.pc",
+                "Version: 1\nCflags: -DBAZ\nLibs: -lbaz\n",
+            ),
+        ]);
+        let read_file = |path: &Path| files.get(path.as_ref()).map(|s| s.as_bytes().to_vec());
+        let pkg_config = PkgConfig {
+            dirs: vec![Path::from("/nowhere"), Path::from("/sysroot/lib/pkgconfig")],
+            sysroot: Some("/sysroot".into()),
+            read_file: &read_file,
+        };
+
+        let foo = pkg_config.resolve("foo", false).unwrap().unwrap();
+        assert_eq!(foo.version, "1.0");
+        assert_eq!(foo.cflags, vec!["-I/sysroot/opt/include", "-DBAR", "-DBAZ"]);
+        assert_eq!(foo.libs, vec!["-L/sysroot/opt/lib", "-lbar", "-lfoo"]);
+
+        let foo = pkg_config.resolve("foo", true).unwrap().unwrap();
+        assert_eq!(
+            foo.libs,
+            vec!["-L/sysroot/opt/lib", "-lm", "-lbar", "-lfoo", "-lbaz"]
+        );
+
+        assert_eq!(pkg_config.resolve("missing", false).unwrap(), None);
+    }
+}
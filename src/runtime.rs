@@ -16,6 +16,10 @@ pub struct MachineInfo {
     pub cpu: String,
     /// The endianness of the system ("little" or "big")
     pub endian: String,
+    /// The kernel (e.g., "linux", "nt", "xnu"), derived from the system if `None`
+    pub kernel: Option<String>,
+    /// The subsystem (e.g., "macos", "ios"), derived from the system if `None`
+    pub subsystem: Option<String>,
 }
 
 /// Result of attempting to compile source code
@@ -190,4 +194,22 @@ pub trait Runtime: 'static {
     /// # Returns
     /// The output from the command including stdout, stderr, and return code
     fn run_command(&self, cmd: &Path, args: &[&str]) -> Result<RunCommandOutput>;
+
+    /// Runs a batch of independent commands and captures their outputs
+    ///
+    /// Used for compiler checks that probe many arguments at once. The
+    /// default implementation runs the commands one after the other;
+    /// runtimes can override it to run them in parallel.
+    ///
+    /// # Arguments
+    /// * `commands` - The commands to run, each with its arguments
+    ///
+    /// # Returns
+    /// The output of each command, in the order the commands were given
+    fn run_commands(&self, commands: &[(&Path, &[&str])]) -> Vec<Result<RunCommandOutput>> {
+        commands
+            .iter()
+            .map(|(cmd, args)| self.run_command(cmd, args))
+            .collect()
+    }
 }